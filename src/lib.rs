@@ -1,7 +1,7 @@
 #![no_std]
 
 use soroban_auth::{Signature, Identifier, verify};
-use soroban_sdk::{contracttype, Env, BigInt, BytesN, contractimpl, contracterror, panic_error, symbol};
+use soroban_sdk::{contracttype, Env, BigInt, Bytes, BytesN, RawVal, contractimpl, contracterror, panic_error, symbol, vec, IntoVal, Status, Symbol, Vec, serde::Serialize};
 
 mod token {
     soroban_sdk::contractimport!(file = "./soroban_token_spec.wasm");
@@ -18,8 +18,61 @@ pub enum Error {
     StreamCancelled = 5,
     StreamNotCancellable = 6,
     StreamDone = 7,
+    InvalidEndTime = 8,
+    StreamNotEnded = 9,
+    AlreadyInit = 10,
+    TokenNotAllowed = 11,
+    NoPendingStop = 12,
+    StopExpired = 13,
+    InvalidSplit = 14,
+    SwapFailed = 15,
+    StartTimeInPast = 16,
+    WithdrawPaused = 17,
+    InvalidReduce = 18,
+    InvalidTick = 19,
+    TooManyStreams = 20,
+    NoEntry = 21,
+    EntryDone = 22,
+    NotDue = 23,
+    GuaranteeViolated = 24,
+    WindingDown = 25,
+    NotWoundDown = 26,
+    DurationTooShort = 27,
+    TickTooShort = 28,
+    NotPrefunded = 29,
+    WithdrawTooSoon = 30,
+    Bonded = 31,
+    InsufficientApproval = 32,
+    NotCorrupted = 33,
+    NotInit = 34,
+    TooEarly = 35,
+    InvalidToken = 36,
+    InvalidMilestones = 37,
+    HasMilestones = 38,
+    StreamNotStarted = 39,
+    InvalidAmount = 40,
+    FeeOnTransferUnsupported = 41,
+    SwapNotAllowed = 42,
 }
 
+// how long a prepared cancellation can be executed, in seconds or ledgers for streams with `by_seq`
+const STOP_WINDOW: u64 = 60;
+
+// time between the admin starting the wind down and streams being settled with `wd_settle`, in seconds
+const WIND_DELAY: u64 = 7 * 24 * 60 * 60;
+
+// time between requesting a cancellation with `req_stop` and executing it with `fin_stop`, in seconds
+const STOP_DELAY: u64 = 24 * 60 * 60;
+
+// time after the end of a stream before the creator can take back what the recipient didn't withdraw, in seconds
+const RECLAIM_GRACE: u64 = 30 * 24 * 60 * 60;
+
+// maximum number of ids returned by paged listings
+const MAX_PAGE: u32 = 100;
+
+// version of the event topics, bump it when the topics or data of an event change
+const EVENT_VERSION: u32 = 1;
+
 #[derive(Clone)]
 #[contracttype]
 pub enum DataKey {
@@ -27,7 +80,140 @@ pub enum DataKey {
     StreamId,
     // extra data relating to withdrawing from the stream
     StreamData(u64),
-    Nonce(Identifier)
+    Nonce(Identifier),
+    Admin,
+    // tokens that streams can be created for, if empty all tokens are allowed
+    Allowed(BytesN<32>),
+    NbAllowed,
+    // amount of a token that is owed to streams
+    Reserved(BytesN<32>),
+    // prepared cancellation of a stream
+    PendStop(u64),
+    // if set, streams can't start more than this many seconds in the past, not checked for streams with `by_seq`
+    StartGrace,
+    // total amount of a token refunded to creators of cancelled streams
+    Refunded(BytesN<32>),
+    // contract that is notified of withdrawals
+    Hook,
+    // if set, recipients can't withdraw. streams can still be cancelled
+    WPaused,
+    // ids of the streams an identity created
+    Sent(Identifier),
+    // ids of the streams an identity is the recipient of
+    Received(Identifier),
+    // tick_time of streams created with a tick_time of 0
+    DefTick,
+    // maximum number of active streams per sender, unlimited if not set
+    MaxStreams,
+    // withdrawals the recipient of a stream pre-authorized
+    Schedule(u64),
+    // identity that may withdraw for the recipient of a stream
+    Delegate(u64),
+    // everything paid out to the recipient of a stream
+    History(u64),
+    // time after which all streams can be settled, set when the contract is wound down
+    WindDown,
+    // identities that can create streams funded by an identity
+    Operators(Identifier),
+    // minimum end_time - start_time of new streams in seconds, not checked for streams with `by_seq`
+    MinDur,
+    // minimum tick_time of new streams in seconds, not checked for streams with `by_seq`
+    MinTick,
+    // id of the stream created by `c_once`, by the hash of the creator and their key
+    SeenKey(BytesN<32>),
+    // time at which the creator requested to cancel a stream with `req_stop`
+    StopReq(u64),
+    // delay between `req_stop` and `fin_stop`, `STOP_DELAY` if not set
+    StopDelay,
+    // time after the end before `reclaim` can be used, `RECLAIM_GRACE` if not set
+    RGrace,
+    // (unlock time, cumulative amount) points of a stream created with `c_mstone`
+    Milestones(u64),
+    // (identity, amount) contributions to a stream that was topped up with `add_fund`, the creator comes first
+    Funders(u64),
+    // ids of the streams between a sender and a recipient, by the hash of the pair
+    Pair(BytesN<32>),
+    // swap contracts the creator of a stream can migrate it with
+    Swap(BytesN<32>),
+    // number of streams of a sender that are neither cancelled nor completed
+    NbActive(Identifier),
+}
+
+// kind of signature a call will be authorized with
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SigKind {
+    Invoker,
+    Ed25519,
+    Account,
+}
+
+// how the amount of a stream is spread over its ticks
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Curve {
+    // the same amount every tick
+    Linear,
+    // after k of n ticks, 1 - ((n - k) / n)^2 of the amount has vested, so every tick releases less than the one before
+    FrontLoad,
+}
+
+// stream given as a rate for `c_rate`, it starts when it is created and lasts `num_ticks` ticks
+#[contracttype]
+#[derive(Clone,Debug)]
+pub struct RateArgs{
+    pub from: Identifier,
+    pub to: Identifier,
+    pub token_c_id: BytesN<32>,
+    // amount that vests every tick
+    pub per_tick: BigInt,
+    pub tick_time: u64,
+    pub num_ticks: u64,
+    pub able_stop: bool,
+}
+
+// state of a stream at the current time
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StreamStatus {
+    // before `start_time`
+    Pending,
+    Active,
+    // after `end_time`, but the recipient didn't withdraw everything yet
+    Ended,
+    Completed,
+    Cancelled,
+}
+
+// amounts of a stream at the current time, returned by `summary`
+#[contracttype]
+#[derive(Clone,Debug)]
+pub struct StreamSummary{
+    pub amount: BigInt,
+    pub withdrawn: BigInt,
+    // `amount - withdrawn`, 0 once the stream is cancelled
+    pub remaining: BigInt,
+    pub vested: BigInt,
+    // what the recipient can withdraw now, `vested - withdrawn`
+    pub claimable: BigInt,
+    pub status: StreamStatus,
+}
+
+// withdrawal at `time` that a keeper can execute once
+#[contracttype]
+#[derive(Clone,Debug)]
+pub struct SchedEntry{
+    pub time: u64,
+    pub done: bool,
+}
+
+// payment to the recipient of a stream. the token is stored as well, since migrating changes the token of a stream
+#[contracttype]
+#[derive(Clone,Debug)]
+pub struct Payout{
+    pub time: u64,
+    pub amount: BigInt,
+    pub token_c_id: BytesN<32>,
 }
 
 #[contracttype]
@@ -36,7 +222,49 @@ pub struct StreamData{
     // how much has been withdrawn
     pub a_withdraw: BigInt,
     // wether the stream was cancelled
-    pub cancelled: bool
+    pub cancelled: bool,
+    // amount that had vested at `start_time`, set when the schedule of the stream is changed
+    pub a_vested: BigInt,
+    // decimals of the token, read from the token when the stream was created or migrated
+    pub decimals: u32,
+    // time of the last withdrawal, 0 if there wasn't one yet
+    pub last_wd: u64,
+    // set once everything has been paid to the recipient
+    pub completed: bool,
+}
+
+// settings of the contract. settings that weren't set have the value that behaves the same
+#[contracttype]
+#[derive(Clone,Debug)]
+pub struct Settings{
+    pub admin: Identifier,
+    // whether withdrawals are paused
+    pub w_paused: bool,
+    // u32::MAX if there is no maximum
+    pub max_strms: u32,
+    // 0 if there is no default
+    pub def_tick: u64,
+    pub min_dur: u64,
+    pub min_tick: u64,
+    // u64::MAX if streams can start at any time in the past
+    pub grace: u64,
+    // all zeros if there is no hook
+    pub hook: BytesN<32>,
+    // time after which streams can be settled with `wd_settle`, 0 if the contract isn't wound down
+    pub wind_down: u64,
+    // delay between `req_stop` and `fin_stop`
+    pub stop_delay: u64,
+    // time after the end of a stream before the creator can `reclaim` it
+    pub r_grace: u64,
+}
+
+#[contracttype]
+#[derive(Clone,Debug)]
+pub struct PendStop{
+    // amount of the stream that goes to the recipient when the cancellation is executed
+    pub vested: BigInt,
+    // the cancellation has to be executed before this time
+    pub expires: u64
 }
 
 #[contracttype]
@@ -52,66 +280,435 @@ pub struct Stream {
     // token contract id
     pub token_c_id : BytesN<32>,
     //whether the creator can cancell the stream.
-    pub able_stop : bool
+    pub able_stop : bool,
+    // the creator can only cancel the stream until this time, after that the stream is irrevocable
+    pub stop_until : u64,
+    // whether anyone can push the remaining funds to the recipient after the stream ended
+    pub auto_push : bool,
+    // the recipient gets at least this much in total, even if the stream is reduced or cancelled early
+    pub min_guar : BigInt,
+    // whether the amount is locked in this contract when the stream is created. otherwise every payout is pulled
+    // from `from` with the allowance of this contract, so the creator keeps the funds until they are paid
+    pub prefunded : bool,
+    // minimum number of seconds between two withdrawals with `w_stream`, 0 for no limit
+    pub min_wd_int : u64,
+    // if set, `start_time`, `end_time`, `tick_time` and `stop_until` are ledger sequence numbers instead of seconds
+    pub by_seq : bool,
+    // locked on top of `amount`. it goes to the recipient if the creator cancels the stream and back to the creator
+    // once the stream is paid out completely
+    pub bond : BigInt,
+    // how the amount vests over the ticks. if the stream is changed later, the curve starts again from the last tick
+    pub curve : Curve,
+    // if `to` is a contract, call `on_recv(stream_id, amount)` on it after every payout, e.g. so that a vault can deposit it
+    pub notify_to : bool,
 }
 
 
 
 pub trait StreamingTrait {
+    // set the admin of the contract
+    fn init(env: Env, admin: Identifier);
+    // all settings of the contract in one call, fails if there is no admin yet
+    fn config(env: Env) -> Settings;
     //create stream
     fn c_stream(env: Env, signature: Signature, nonce: BigInt, stream : Stream) -> u64;
+    // like `c_stream`, but calling it again with the same key returns the stream created the first time
+    fn c_once(env: Env, signature: Signature, nonce: BigInt, stream : Stream, key: BytesN<32>) -> u64;
+    // create a stream that releases `milestones` of (unlock time, cumulative amount) instead of a fixed amount per tick
+    fn c_mstone(env: Env, signature: Signature, nonce: BigInt, stream : Stream, milestones: Vec<(u64, BigInt)>) -> u64;
+    // create stream, funded with a `xfer` signed by the creator instead of an allowance
+    fn c_direct(env: Env, signature: Signature, nonce: BigInt, stream : Stream, xfer_sig: Signature, xfer_nonce: BigInt) -> u64;
+    // create a stream starting now that pays `per_tick` every `tick_time` seconds for `num_ticks` ticks
+    fn c_rate(env: Env, signature: Signature, nonce: BigInt, rate: RateArgs) -> u64;
+    // create a stream for each recipient, splitting the amount of `base` by weight
+    fn c_split(env: Env, signature: Signature, nonce: BigInt, base: Stream, recipients: Vec<(Identifier, u32)>) -> Vec<u64>;
+    // allow or disallow `operator` to create streams funded with the allowance of the caller
+    fn set_oper(env: Env, signature: Signature, nonce: BigInt, operator: Identifier, allowed: bool);
     // withdraw from streaam
     fn w_stream(env: Env, signature: Signature, nonce: BigInt, stream_id : u64);
+    // withdraw from every active stream of the last `MAX_PAGE` streams the signer received
+    fn w_all(env: Env, signature: Signature, nonce: BigInt);
+    // let `delegate` withdraw to the recipient
+    fn set_deleg(env: Env, signature: Signature, nonce: BigInt, stream_id : u64, delegate: Identifier);
+    // revoke the delegate of the recipient
+    fn clr_deleg(env: Env, signature: Signature, nonce: BigInt, stream_id : u64);
+    // pre-authorize withdrawals at the given times, that anyone can execute with `exec_entry`
+    fn reg_sched(env: Env, signature: Signature, nonce: BigInt, stream_id : u64, times: Vec<u64>);
+    // recipient moves `fraction_bps` / 10000 of what hasn't vested yet to a new stream to `new_to`, returns its id
+    fn split_to(env: Env, signature: Signature, nonce: BigInt, stream_id : u64, new_to: Identifier, fraction_bps: u32) -> u64;
+    // withdraw for the recipient according to entry `index` of the registered schedule
+    fn exec_entry(env: Env, stream_id : u64, index: u32);
     //cancell/stop stream
-    fn s_stream(env: Env, signature: Signature, stream_id : u64);
+    fn s_stream(env: Env, signature: Signature, nonce: BigInt, stream_id : u64);
+    // cancel every cancellable stream of the last `MAX_PAGE` streams the signer created
+    fn cancel_all(env: Env, signature: Signature, nonce: BigInt);
+    // make a stream cancellable by its creator, which the recipient has to sign as well
+    fn en_cancel(env: Env, signature: Signature, nonce: BigInt, stream_id : u64, to_sig: Signature, to_nonce: BigInt);
+    // recipient gives up the stream, what has vested is paid out and the rest goes back to the creator
+    fn renounce(env: Env, signature: Signature, nonce: BigInt, stream_id : u64);
+    // extend the end of the stream, spreading the remaining funds over a longer period
+    fn e_stream(env: Env, signature: Signature, nonce: BigInt, stream_id : u64, end_time: u64);
+    // change the tick time of the rest of the stream, the amount and the end stay the same
+    fn retick(env: Env, signature: Signature, nonce: BigInt, stream_id : u64, tick_time: u64);
+    // take back `reduce_by` of the unvested funds, ending the stream earlier at the same rate
+    fn r_stream(env: Env, signature: Signature, nonce: BigInt, stream_id : u64, reduce_by: BigInt);
+    // add `amount` of the signer to a prefunded stream, refunds are then split over everyone who funded it
+    fn add_fund(env: Env, signature: Signature, nonce: BigInt, stream_id : u64, amount: BigInt);
+    // (refund, payout) of cancelling the stream now, (0, 0) if it can't be cancelled
+    fn prev_stop(env: Env, stream_id : u64) -> (BigInt, BigInt);
+    // compute and store the (refund, payout) of cancelling the stream now
+    fn prep_stop(env: Env, signature: Signature, nonce: BigInt, stream_id : u64) -> (BigInt, BigInt);
+    // cancel the stream using the settlement from `prep_stop`
+    fn exec_stop(env: Env, signature: Signature, nonce: BigInt, stream_id : u64);
+    // announce that the stream will be cancelled, the recipient can still withdraw until `fin_stop`
+    fn req_stop(env: Env, signature: Signature, nonce: BigInt, stream_id : u64);
+    // cancel a stream once the delay after `req_stop` has passed
+    fn fin_stop(env: Env, signature: Signature, nonce: BigInt, stream_id : u64);
+    // send the rest of an ended stream to the recipient, if the stream has `auto_push` set
+    fn finalize(env: Env, stream_id : u64);
+    // withdraw the rest of an ended stream and remove it
+    fn cl_stream(env: Env, signature: Signature, nonce: BigInt, stream_id : u64);
+    // take back what the recipient didn't withdraw, once the stream ended more than the reclaim grace ago
+    fn reclaim(env: Env, signature: Signature, nonce: BigInt, stream_id : u64);
+    // swap what is left of the stream to another token
+    fn migrate(env: Env, signature: Signature, nonce: BigInt, stream_id : u64, token_c_id: BytesN<32>, swap_c_id: BytesN<32>);
 
     fn get_stream(env: Env, stream_id : u64) -> (Stream,StreamData);
+    // the amounts and status of the stream at the current time
+    fn summary(env: Env, stream_id: u64) -> StreamSummary;
+    // `get_stream` for the first `MAX_PAGE` ids, ids of streams that don't exist, were closed or are corrupted are skipped
+    fn get_many(env: Env, stream_ids: Vec<u64>) -> Vec<(Stream,StreamData)>;
+    // whether the sha256 of the serialized `Stream` currently stored for `stream_id` is `expected`
+    fn chk_hash(env: Env, stream_id: u64, expected: BytesN<32>) -> bool;
+    // sha256 of the serialized `Stream`, the fingerprint that is signed when creating a stream
+    fn fprint(env: Env, stream: Stream) -> BytesN<32>;
+    // (amount, decimals of the token) of a stream, so that clients can format the amount
+    fn amt_disp(env: Env, stream_id: u64) -> (BigInt, u32);
     fn nonce(env: Env, id: Identifier) -> BigInt;
+    // `nonce` of every id, in the same order
+    fn nonces(env: Env, ids: Vec<Identifier>) -> Vec<BigInt>;
+    // nonce to pass to the next call of `id` signed with a signature of the given kind
+    fn exp_nonce(env: Env, kind: SigKind, id: Identifier) -> BigInt;
+    // amount the recipient can withdraw now, fails with `StreamNotStarted` before the start
+    fn claimable(env: Env, stream_id: u64) -> BigInt;
+    // total amount vested so far, including what was already withdrawn. fails with `StreamNotStarted` before the start
+    fn accrued(env: Env, stream_id: u64) -> BigInt;
+    // earliest time at which `amount` of the stream has vested, `end_time` if the stream is smaller than that
+    fn vest_time(env: Env, stream_id: u64, amount: BigInt) -> u64;
+    // amount that vests between `from_time` and `to_time`, 0 if `from_time` is after `to_time`
+    fn payout_btw(env: Env, stream_id: u64, from_time: u64, to_time: u64) -> BigInt;
+    // amount the creator would get back by cancelling now, 0 if the stream can't be cancelled
+    fn refundable(env: Env, stream_id: u64) -> BigInt;
+    // whether `id` may withdraw from the stream with `w_stream` now, false if the stream doesn't exist
+    fn can_wd(env: Env, stream_id: u64, id: Identifier) -> bool;
+    // whether `id` may cancel the stream with `s_stream` now, false if the stream doesn't exist
+    fn can_stop(env: Env, stream_id: u64, id: Identifier) -> bool;
+    // amount that vests at the next tick, 0 once the stream ended. fails with `HasMilestones` for milestone streams
+    fn rate(env: Env, stream_id: u64) -> BigInt;
+    // (seconds until the next tick, seconds until the end, index of the current tick)
+    fn timing(env: Env, stream_id: u64) -> (u64, u64, u64);
+    // part of the stream that is left over by rounding, always 0 since the last tick vests the rest
+    fn dust(env: Env, stream_id: u64) -> BigInt;
+    // amount the recipient could withdraw now if the stream was extended to `end_time`
+    fn claim_ext(env: Env, stream_id: u64, end_time: u64) -> BigInt;
+    // ids in [start_id, start_id + limit) whose stream data can't be read
+    fn corrupted(env: Env, start_id: u64, limit: u64) -> Vec<u64>;
+    // serialized value stored as the stream data of a stream, without decoding it. admin only
+    fn raw_data(env: Env, signature: Signature, stream_id: u64) -> Option<Bytes>;
+    // ids of the streams where `id` is the sender or the recipient, in creation order
+    fn my_streams(env: Env, id: Identifier) -> Vec<u64>;
+    // ids of the streams from `from` to `to`, in creation order
+    fn between(env: Env, from: Identifier, to: Identifier) -> Vec<u64>;
+    // ids of the streams to `to` that are neither cancelled nor fully withdrawn, oldest first
+    fn active_in(env: Env, to: Identifier) -> Vec<u64>;
+    // total paid out in a token to `to` between `from_time` (inclusive) and `to_time` (exclusive), over at most `limit`
+    // of the streams they received starting at position `start`
+    fn income(env: Env, to: Identifier, token_c_id: BytesN<32>, from_time: u64, to_time: u64, start: u32, limit: u32) -> BigInt;
+    // at most `limit` ids of the streams created by `from`, starting at position `start`
+    fn sent_page(env: Env, from: Identifier, start: u32, limit: u32) -> Vec<u64>;
+    // how much of a token vests in each of the next `nb_buckets` windows of `bucket_size` seconds, over the active streams
+    // with ids in [start_id, start_id + limit)
+    fn ladder(env: Env, token_c_id: BytesN<32>, bucket_size: u64, nb_buckets: u32, start_id: u64, limit: u64) -> Vec<BigInt>;
+    // number of different recipients of the active streams of `from`
+    fn nb_recips(env: Env, from: Identifier) -> u32;
+    // end_time of the active streams of `from` in a token, weighted by amount. 0 if there are none
+    fn avg_end(env: Env, from: Identifier, token_c_id: BytesN<32>) -> u64;
+
+    // add a token to the whitelist
+    fn allow(env: Env, signature: Signature, nonce: BigInt, token_c_id: BytesN<32>);
+    // remove a token from the whitelist
+    fn disallow(env: Env, signature: Signature, nonce: BigInt, token_c_id: BytesN<32>);
+
+    // reject streams that start more than `grace` seconds in the past, if `enabled`
+    fn set_grace(env: Env, signature: Signature, nonce: BigInt, enabled: bool, grace: u64);
+
+    // set or clear the tick_time used for streams created with a tick_time of 0
+    fn set_dtick(env: Env, signature: Signature, nonce: BigInt, tick_time: Option<u64>);
+
+    // set or clear the minimum duration and tick_time of new streams
+    fn set_mins(env: Env, signature: Signature, nonce: BigInt, duration: Option<u64>, tick_time: Option<u64>);
+
+    // set or clear the maximum number of active streams a sender can have
+    fn set_max(env: Env, signature: Signature, nonce: BigInt, max: Option<u32>);
+
+    // set or clear the contract that gets `on_wdraw(stream_id, amount)` called after every withdrawal
+    fn set_hook(env: Env, signature: Signature, nonce: BigInt, hook: Option<BytesN<32>>);
+
+    // allow or disallow creators to migrate their streams with the swap contract `swap_c_id`
+    fn allow_swap(env: Env, signature: Signature, nonce: BigInt, swap_c_id: BytesN<32>, allowed: bool);
+
+    // pause or unpause withdrawals to recipients, e.g. during an incident. cancelling streams still works
+    fn set_wpause(env: Env, signature: Signature, nonce: BigInt, paused: bool);
+
+    // set the delay between `req_stop` and `fin_stop`, `None` to use `STOP_DELAY`
+    fn set_sdelay(env: Env, signature: Signature, nonce: BigInt, delay: Option<u64>);
+
+    // set the time after the end of a stream before `reclaim` can be used, `None` to use `RECLAIM_GRACE`
+    fn set_rgrace(env: Env, signature: Signature, nonce: BigInt, grace: Option<u64>);
+
+    // stop the creation of streams and allow all streams to be settled with `wd_settle` after `WIND_DELAY`
+    fn wind_down(env: Env, signature: Signature, nonce: BigInt);
+    // settle a stream once the contract is wound down, the recipient gets what has vested and the creator the rest
+    fn wd_settle(env: Env, stream_id: u64);
+
+    // settle a stream with what the contract still holds, if the token took funds away from the contract
+    // (e.g. a clawback) and the stream can't be paid out in full anymore. returns whether the stream was settled
+    fn reconcile(env: Env, signature: Signature, nonce: BigInt, stream_id: u64) -> bool;
+
+    // replace stream data that can't be read, see `corrupted`, with what was withdrawn and whether it was cancelled
+    fn repair(env: Env, signature: Signature, nonce: BigInt, stream_id: u64, a_withdraw: BigInt, cancelled: bool);
+
+    // send the balance of a token that isn't reserved by streams to `dest`
+    fn sweep(env: Env, signature: Signature, nonce: BigInt, token_c_id: BytesN<32>, dest: Identifier);
+
+    // (reserved, free) balance of a token held by the contract, free is 0 if the contract holds less than it owes
+    fn breakdown(env: Env, token_c_id: BytesN<32>) -> (BigInt, BigInt);
+    // total amount of a token refunded to creators of cancelled streams
+    fn refunded(env: Env, token_c_id: BytesN<32>) -> BigInt;
+    // total amount of a token locked in streams that hasn't been withdrawn or refunded
+    fn tvl(env: Env, token_c_id: BytesN<32>) -> BigInt;
 }
 
 pub struct  StreamingContract;
 
 #[contractimpl]
 impl StreamingTrait for StreamingContract{
-    // create the stream by sending withdrawable funds to this contract
+    fn init(env: Env, admin: Identifier){
+        if env.data().has(DataKey::Admin){
+            panic_error!(&env, Error::AlreadyInit);
+        }
+        env.data().set(DataKey::Admin, admin);
+    }
+    fn config(env: Env) -> Settings{
+        let admin = match get_setting(&env, DataKey::Admin){
+            Some(admin) => admin,
+            None => panic_error!(&env, Error::NotInit),
+        };
+        Settings{
+            admin,
+            w_paused: env.data().has(DataKey::WPaused),
+            max_strms: get_setting(&env, DataKey::MaxStreams).unwrap_or(u32::MAX),
+            def_tick: get_setting(&env, DataKey::DefTick).unwrap_or(0),
+            min_dur: get_setting(&env, DataKey::MinDur).unwrap_or(0),
+            min_tick: get_setting(&env, DataKey::MinTick).unwrap_or(0),
+            grace: get_setting(&env, DataKey::StartGrace).unwrap_or(u64::MAX),
+            hook: get_setting(&env, DataKey::Hook).unwrap_or_else(|| BytesN::from_array(&env, &[0; 32])),
+            wind_down: get_setting(&env, DataKey::WindDown).unwrap_or(0),
+            stop_delay: get_stop_delay(&env),
+            r_grace: get_setting(&env, DataKey::RGrace).unwrap_or(RECLAIM_GRACE),
+        }
+    }
+    // create the stream by sending withdrawable funds to this contract.
+    // `stream.from` may be the same as `stream.to`, e.g. to vest your own funds, the stream then shows up once in `my_streams`
     // returns the id of the created stream
     fn c_stream(env: Env, signature: Signature, nonce: BigInt, stream : Stream) -> u64 {
         let id = signature.identifier(&env);
 
-        // check that the signature is valid
-        verify(&env, &signature, symbol!("c_stream"), (&id, &nonce));
+        // check that the signature is valid, the whole stream is signed so that it can't be changed by whoever submits it
+        verify(&env, &signature, symbol!("c_stream"), (&id, &nonce, fingerprint(&env, &stream)));
 
         //consume and check that nonce is valid
         verify_and_consume_nonce(&env, &signature, &nonce);
 
-        token::Client::new(&env, stream.token_c_id.clone())
-        .xfer_from(&soroban_auth::Signature::Invoker, &BigInt::from_u32(&env, 0),&stream.from ,&soroban_auth::Identifier::Contract(env.current_contract()), &stream.amount);
+        // the allowance of `stream.from` is spent, so only they or an operator they set can create the stream
+        check_funder(&env, &id, &stream.from);
+
+        create_stream(&env, stream, &Signature::Invoker, &BigInt::zero(&env))
+    }
+    // the times depend on when the call is executed, so the arguments are signed instead of the stream. the other
+    // options of the stream have their defaults
+    fn c_rate(env: Env, signature: Signature, nonce: BigInt, rate: RateArgs) -> u64 {
+        let id = signature.identifier(&env);
 
-        let stream_id = get_and_inc_stream_id(&env);
+        verify(&env, &signature, symbol!("c_rate"), (&id, &nonce, &rate));
+        verify_and_consume_nonce(&env, &signature, &nonce);
 
-        // store stream
-        env.data()
-        .set(DataKey::Stream(stream_id),stream);
+        check_funder(&env, &id, &rate.from);
 
-        // store mutable stream data
-        env.data()
-        .set(DataKey::StreamData(stream_id), StreamData{
-            a_withdraw: BigInt::zero(&env),
-            cancelled: false 
-        });
+        if rate.per_tick <= BigInt::zero(&env){
+            panic_error!(&env, Error::InvalidAmount);
+        }
+        if rate.num_ticks == 0{
+            panic_error!(&env, Error::InvalidEndTime);
+        }
+        // the end depends on the tick, so the default has to be looked up here already
+        let tick_time = resolve_tick(&env, rate.tick_time);
+
+        let now = env.ledger().timestamp();
+        let end_time = match tick_time.checked_mul(rate.num_ticks).and_then(|duration| now.checked_add(duration)){
+            Some(end_time) => end_time,
+            None => panic_error!(&env, Error::InvalidEndTime),
+        };
+        let stream = Stream{
+            from: rate.from,
+            to: rate.to,
+            amount: rate.per_tick * rate.num_ticks,
+            start_time: now,
+            end_time,
+            tick_time,
+            token_c_id: rate.token_c_id,
+            able_stop: rate.able_stop,
+            stop_until: u64::MAX,
+            auto_push: false,
+            min_guar: BigInt::zero(&env),
+            prefunded: true,
+            min_wd_int: 0,
+            by_seq: false,
+            bond: BigInt::zero(&env),
+            curve: Curve::Linear,
+            notify_to: false,
+        };
+
+        create_stream(&env, stream, &Signature::Invoker, &BigInt::zero(&env))
+    }
+    // the key is scoped to `stream.from`, so others can't take the keys of a creator
+    fn c_once(env: Env, signature: Signature, nonce: BigInt, stream : Stream, key: BytesN<32>) -> u64 {
+        let id = signature.identifier(&env);
+
+        verify(&env, &signature, symbol!("c_once"), (&id, &nonce, fingerprint(&env, &stream), &key));
+
+        check_funder(&env, &id, &stream.from);
+
+        // a retried submission is answered before the nonce is checked, since the first one already used it
+        let seen_key = DataKey::SeenKey(env.compute_hash_sha256(&(stream.from.clone(), key).serialize(&env)));
+        let seen: Option<Result<u64, _>> = env.data().get(seen_key.clone());
+        if let Some(Ok(stream_id)) = seen{
+            return stream_id;
+        }
+
+        verify_and_consume_nonce(&env, &signature, &nonce);
 
-        //return stream id
+        let stream_id = create_stream(&env, stream, &Signature::Invoker, &BigInt::zero(&env));
+        env.data().set(seen_key, stream_id);
         stream_id
     }
+    // the times of the milestones have to increase, the amounts can't decrease and the last amount has to be
+    // the amount of the stream. after `end_time` everything has vested, like for other streams
+    fn c_mstone(env: Env, signature: Signature, nonce: BigInt, stream : Stream, milestones: Vec<(u64, BigInt)>) -> u64 {
+        let id = signature.identifier(&env);
+
+        verify(&env, &signature, symbol!("c_mstone"), (&id, &nonce, fingerprint(&env, &stream), &milestones));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        check_funder(&env, &id, &stream.from);
+        check_milestones(&env, &stream, &milestones);
+
+        let stream_id = create_stream(&env, stream, &Signature::Invoker, &BigInt::zero(&env));
+        env.data().set(DataKey::Milestones(stream_id), milestones);
+        stream_id
+    }
+    // like `c_stream`, but the funds are sent with a `xfer` signed by `stream.from`, so no allowance is needed
+    fn c_direct(env: Env, signature: Signature, nonce: BigInt, stream : Stream, xfer_sig: Signature, xfer_nonce: BigInt) -> u64 {
+        let id = signature.identifier(&env);
+
+        // check that the signature is valid, the whole stream is signed since `xfer_sig` only covers the amount
+        verify(&env, &signature, symbol!("c_direct"), (&id, &nonce, fingerprint(&env, &stream)));
+
+        //consume and check that nonce is valid
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        check_funder(&env, &id, &stream.from);
+
+        // an invoker signature for `xfer` would be a transfer from this contract
+        if let Signature::Invoker = xfer_sig{
+            panic_error!(&env, Error::NotAuthorized);
+        }
+
+        create_stream(&env, stream, &xfer_sig, &xfer_nonce)
+    }
+    // split `base` over multiple recipients, every recipient gets a stream with a share of `base.amount` proportional to its weight.
+    // the remainder of the division goes to the first recipient.
+    // returns the ids of the created streams, in the same order as `recipients`
+    fn c_split(env: Env, signature: Signature, nonce: BigInt, base: Stream, recipients: Vec<(Identifier, u32)>) -> Vec<u64> {
+        let id = signature.identifier(&env);
+
+        // check that the signature is valid, the base stream and the recipients are signed so that they can't be
+        // changed by whoever submits it
+        verify(&env, &signature, symbol!("c_split"), (&id, &nonce, fingerprint(&env, &base), &recipients));
+
+        //consume and check that nonce is valid
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        check_funder(&env, &id, &base.from);
+
+        // a recipient without weight would get an empty stream that still locks part of the bond
+        let mut total_weight = 0u64;
+        for recipient in recipients.iter(){
+            let (_, weight) = recipient.unwrap();
+            if weight == 0{
+                panic_error!(&env, Error::InvalidSplit);
+            }
+            total_weight += weight as u64;
+        }
+        if total_weight == 0{
+            panic_error!(&env, Error::InvalidSplit);
+        }
+
+        // the amount, bond and guarantee are split by weight, the remainders go to the first recipient
+        let amounts = split_by_weight(&env, &base.amount, &recipients, total_weight);
+        let bonds = split_by_weight(&env, &base.bond, &recipients, total_weight);
+        let guarantees = split_by_weight(&env, &base.min_guar, &recipients, total_weight);
+
+        let mut stream_ids = Vec::new(&env);
+        for (i, recipient) in recipients.iter().enumerate(){
+            let (to, _) = recipient.unwrap();
+            let i = i as u32;
+
+            stream_ids.push_back(create_stream(&env, Stream{
+                to,
+                amount: amounts.get_unchecked(i).unwrap(),
+                bond: bonds.get_unchecked(i).unwrap(),
+                min_guar: guarantees.get_unchecked(i).unwrap(),
+                ..base.clone()
+            }, &Signature::Invoker, &BigInt::zero(&env)));
+        }
+        stream_ids
+    }
+    fn set_oper(env: Env, signature: Signature, nonce: BigInt, operator: Identifier, allowed: bool){
+        let id = signature.identifier(&env);
+
+        verify(&env, &signature, symbol!("set_oper"), (&id, &nonce, &operator, allowed));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        let mut operators = get_operators(&env, &id);
+        match (operators.first_index_of(&operator), allowed){
+            (None, true) => operators.push_back(operator),
+            (Some(i), false) => operators.remove_unchecked(i),
+            _ => {},
+        }
+        env.data().set(DataKey::Operators(id), operators);
+    }
     // withdraw from stream
     fn w_stream(env: Env, signature: Signature, nonce: BigInt, stream_id: u64){
+        check_wd_not_paused(&env);
+
         let stream = get_stream(&env, stream_id);
         let stream_data = get_stream_data(&env, stream_id);
 
         let id = signature.identifier(&env);
 
-        //check if user is the recipient of the stream
-        if id != stream.to{
+        //check if user is the recipient of the stream or its delegate
+        if id != stream.to && Some(Ok(id.clone())) != env.data().get(DataKey::Delegate(stream_id)){
             panic_error!(&env, Error::NotAuthorized);
         }
 
@@ -120,139 +717,2250 @@ impl StreamingTrait for StreamingContract{
             panic_error!(&env, Error::StreamCancelled);
         }
 
-        // check if all tokens have been withdrawn
-        if stream_data.a_withdraw == stream.amount{
+        // check if all tokens have been withdrawn. `a_withdraw` is checked as well, since it can't go back under the
+        // amount once it is over it
+        if stream_data.completed || stream_data.a_withdraw >= stream.amount{
             panic_error!(&env, Error::StreamDone);
         }
 
-        // check that the signature is valid
-        verify(&env, &signature, symbol!("w_stream"), (&id, &nonce));
+        check_started(&env, &stream);
+
+        // limits how fast a leaked key of the recipient can drain the stream
+        if stream_data.last_wd != 0 && env.ledger().timestamp() < stream_data.last_wd.saturating_add(stream.min_wd_int){
+            panic_error!(&env, Error::WithdrawTooSoon);
+        }
+
+        // check that the signature is valid.
+        // the stream id is signed as well, so a detached signature handed to a relayer can only be used for this stream.
+        // the funds always go to the recipient, no matter who submits the withdrawal.
+        verify(&env, &signature, symbol!("w_stream"), (&id, &nonce, id_fingerprint(&env, stream_id)));
 
         //consume and check that nonce is valid
         verify_and_consume_nonce(&env, &signature, &nonce);
 
+        withdraw(&env, stream_id, &stream, &stream_data);
+    }
+    // only the recipient can collect, delegates have to use `w_stream`.
+    // streams with nothing to withdraw or that were withdrawn from too recently are skipped
+    fn w_all(env: Env, signature: Signature, nonce: BigInt){
+        check_wd_not_paused(&env);
+
+        let id = signature.identifier(&env);
+
+        verify(&env, &signature, symbol!("w_all"), (&id, &nonce));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        let received = get_index(&env, DataKey::Received(id));
+        let start = received.len().saturating_sub(MAX_PAGE);
+
+        for stream_id in received.slice(start..received.len()).iter(){
+            let stream_id = stream_id.unwrap();
+            let stream = match get_active(&env, stream_id){
+                Some(stream) => stream,
+                None => continue,
+            };
+            let stream_data = get_stream_data(&env, stream_id);
 
-        // if we are over the end of the stream, then withdraw everything.
-        if stream.end_time < env.ledger().timestamp(){
-            token::Client::new(&env, stream.token_c_id.clone())
-                .xfer(&Signature::Invoker, &BigInt::zero(&env), &stream.to, &(&stream.amount - &stream_data.a_withdraw));
+            if stream_data.last_wd != 0 && env.ledger().timestamp() < stream_data.last_wd.saturating_add(stream.min_wd_int){
+                continue;
+            }
+            if get_claimable(&env, stream_id, &stream, &stream_data, stream_now(&env, &stream)) == BigInt::zero(&env){
+                continue;
+            }
 
-            update_amount_withdrawn(&env, stream_id, stream.amount);
-            return
+            withdraw(&env, stream_id, &stream, &stream_data);
         }
+    }
+    fn set_deleg(env: Env, signature: Signature, nonce: BigInt, stream_id: u64, delegate: Identifier){
+        let stream = get_stream(&env, stream_id);
 
-        // stream duration
-        let duration = stream.end_time - stream.start_time;
+        let id = signature.identifier(&env);
 
-        let mut total_ticks = duration / stream.tick_time;
-        // round up the total ticks
-        if duration % stream.tick_time != 0{
-            total_ticks += 1;
+        if id != stream.to{
+            panic_error!(&env, Error::NotAuthorized);
         }
-        let amount_per_tick = stream.amount / total_ticks;
 
-        let time_elapsed = env.ledger().timestamp() - stream.start_time;
-        // elsapsed ticks
-        let elapsed_ticks = time_elapsed / stream.tick_time;
+        verify(&env, &signature, symbol!("set_deleg"), (&id, &nonce, stream_id, &delegate));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        env.data().set(DataKey::Delegate(stream_id), delegate);
+    }
+    fn clr_deleg(env: Env, signature: Signature, nonce: BigInt, stream_id: u64){
+        let stream = get_stream(&env, stream_id);
 
-        // get the amount of funds that we can withdraw minus the amount we have allready withdrawn
-        let amount_to_withdraw = amount_per_tick * elapsed_ticks - &stream_data.a_withdraw;
+        let id = signature.identifier(&env);
 
-        // don't invoke the token contract if amount == 0
-        if amount_to_withdraw == BigInt::zero(&env) {
-            return;
+        if id != stream.to{
+            panic_error!(&env, Error::NotAuthorized);
         }
 
-        token::Client::new(&env, stream.token_c_id.clone())
-        .xfer(&Signature::Invoker, &BigInt::zero(&env), &stream.to, &amount_to_withdraw);
+        verify(&env, &signature, symbol!("clr_deleg"), (&id, &nonce, stream_id));
+        verify_and_consume_nonce(&env, &signature, &nonce);
 
-        update_amount_withdrawn(&env, stream_id, &stream_data.a_withdraw + &amount_to_withdraw);
+        env.data().remove(DataKey::Delegate(stream_id));
     }
-    //stop stream if it is cancellable and return the available funds back to the creataor of the stream
-    fn s_stream(env: Env, signature: Signature, stream_id: u64){
+    // the recipient signs the times at which a keeper may withdraw for them, replacing the previous schedule
+    fn reg_sched(env: Env, signature: Signature, nonce: BigInt, stream_id: u64, times: Vec<u64>){
         let stream = get_stream(&env, stream_id);
-        let stream_data = get_stream_data(&env, stream_id);
 
         let id = signature.identifier(&env);
 
-        // check if creator of stream
-        if stream.from != id{
+        if id != stream.to{
             panic_error!(&env, Error::NotAuthorized);
-        } 
+        }
 
-        // check if stream is cancellable
-        if !stream.able_stop{
-            panic_error!(&env, Error::StreamNotCancellable);
+        verify(&env, &signature, symbol!("reg_sched"), (&id, &nonce, stream_id, &times));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        let mut schedule = Vec::new(&env);
+        for time in times.iter(){
+            schedule.push_back(SchedEntry{
+                time: time.unwrap(),
+                done: false,
+            });
         }
-        // check if stream is allready cancelled
+        env.data().set(DataKey::Schedule(stream_id), schedule);
+    }
+    // the new stream has the same schedule and is funded by what was locked for the original, so no tokens move.
+    // the bond, guarantee, delegate and withdrawal schedule stay with the original stream
+    fn split_to(env: Env, signature: Signature, nonce: BigInt, stream_id: u64, new_to: Identifier, fraction_bps: u32) -> u64{
+        check_no_milestones(&env, stream_id);
+
+        let mut stream = get_stream(&env, stream_id);
+        let mut stream_data = get_stream_data(&env, stream_id);
+
+        let id = signature.identifier(&env);
+
+        if id != stream.to{
+            panic_error!(&env, Error::NotAuthorized);
+        }
+
+        verify(&env, &signature, symbol!("split_to"), (&id, &nonce, stream_id, &new_to, fraction_bps));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
         if stream_data.cancelled{
             panic_error!(&env, Error::StreamCancelled);
         }
-        // dont need nonce, since we can only stop once.
-        verify(&env, &signature, symbol!("s_stream"), (&id, stream_id));
+        if stream_data.completed{
+            panic_error!(&env, Error::StreamDone);
+        }
+        if fraction_bps == 0 || fraction_bps > 10000{
+            panic_error!(&env, Error::InvalidSplit);
+        }
 
-        // send back everything that wasn't withdrawn
-        token::Client::new(&env, stream.token_c_id.clone())
-                .xfer(&Signature::Invoker, &BigInt::zero(&env), &id, &(&stream.amount - &stream_data.a_withdraw));
+        // after the end everything has vested, and rebasing would move `start_time` past `end_time`
+        let now = stream_now(&env, &stream);
+        if now >= stream.end_time{
+            panic_error!(&env, Error::StreamDone);
+        }
 
-        set_stream_data_cancelled(&env, stream_id);
-    }
-    // retrieve stream and additional stream data
-    fn get_stream(env: Env, stream_id: u64) -> (Stream,StreamData){
-        (get_stream(&env, stream_id), get_stream_data(&env, stream_id))
+        // what has vested stays with the recipient
+        rebase(&mut stream, &mut stream_data, now);
+
+        // a stream of 0 would never complete and keep taking up a slot of the sender
+        let moved = (&stream.amount - &stream_data.a_vested) * fraction_bps / 10000u32;
+        if moved == BigInt::zero(&env){
+            panic_error!(&env, Error::InvalidSplit);
+        }
+        stream.amount = &stream.amount - &moved;
+        if stream.min_guar > stream.amount{
+            stream.min_guar = stream.amount.clone();
+        }
+        stream_data.completed = stream_data.a_withdraw >= stream.amount;
+
+        let new_stream = Stream{
+            to: new_to,
+            amount: moved,
+            min_guar: BigInt::zero(&env),
+            bond: BigInt::zero(&env),
+            ..stream.clone()
+        };
+        let decimals = stream_data.decimals;
+        let completed = stream_data.completed;
+
+        env.data().set(DataKey::Stream(stream_id), stream.clone());
+        put_stream_data(&env, stream_id, &stream, stream_data);
+
+        // moving everything that is left completes the stream, and nothing else would release its bond after that
+        if completed{
+            release_bond(&env, &stream, &stream.from);
+        }
+
+        let new_id = store_stream(&env, new_stream, decimals);
+
+        // refunds are split by the relative contributions, so the new stream refunds the same funders
+        let funders = get_funders(&env, stream_id);
+        if !funders.is_empty(){
+            env.data().set(DataKey::Funders(new_id), funders);
+        }
+
+        new_id
     }
+    // anyone can execute an entry of the schedule once its time has come, the funds go to the recipient
+    fn exec_entry(env: Env, stream_id: u64, index: u32){
+        check_wd_not_paused(&env);
 
-    fn nonce(env: Env, id: Identifier) -> BigInt {
-        get_nonce(&env, &id)
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+
+        let schedule: Option<Result<Vec<SchedEntry>, _>> = env.data().get(DataKey::Schedule(stream_id));
+        let mut schedule = match schedule{
+            Some(Ok(schedule)) if index < schedule.len() => schedule,
+            _ => panic_error!(&env, Error::NoEntry),
+        };
+
+        let entry = schedule.get_unchecked(index).unwrap();
+        if entry.done{
+            panic_error!(&env, Error::EntryDone);
+        }
+        if env.ledger().timestamp() < entry.time{
+            panic_error!(&env, Error::NotDue);
+        }
+
+        if stream_data.cancelled{
+            panic_error!(&env, Error::StreamCancelled);
+        }
+        if stream_data.completed{
+            panic_error!(&env, Error::StreamDone);
+        }
+
+        schedule.set(index, SchedEntry{
+            done: true,
+            ..entry
+        });
+        env.data().set(DataKey::Schedule(stream_id), schedule);
+
+        withdraw(&env, stream_id, &stream, &stream_data);
     }
-}
-fn get_and_inc_stream_id(env: &Env) -> u64 {
-    let prev = env
-        .data()
-        .get(DataKey::StreamId)
-        .unwrap_or(Ok(0u64))
-        .unwrap();
+    //stop stream if it is cancellable, the recipient gets what has vested and the rest goes back to the creataor of the stream
+    fn s_stream(env: Env, signature: Signature, nonce: BigInt, stream_id: u64){
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
 
-    env.data().set(DataKey::StreamId, prev + 1);
-    prev
-}
+        let id = signature.identifier(&env);
 
-fn get_stream(env: &Env, stream_id: u64) -> Stream{
-    let data: Option<Result<Stream, _>> = env.data()
-        .get(DataKey::Stream(stream_id));
-    
-    match data{
-        Some(Ok(stream)) => stream,
-        _ => panic_error!(&env,Error::StreamNotExist),
+        // consume the nonce before the other checks, so that a replayed signature fails with `IncorrectNonce`
+        verify(&env, &signature, symbol!("s_stream"), (&id, &nonce, id_fingerprint(&env, stream_id)));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        check_cancellable(&env, &stream, &stream_data, &id);
+
+        let vested = get_vested(&env, stream_id, &stream, &stream_data, stream_now(&env, &stream));
+        settle(&env, stream_id, &stream, &stream_data, &vested, &stream.to);
     }
-}
+    // streams that can't be cancelled are skipped. a failing settlement reverts the whole call, so either every
+    // cancellable stream is stopped or none is
+    fn cancel_all(env: Env, signature: Signature, nonce: BigInt){
+        let id = signature.identifier(&env);
 
-fn get_stream_data(env: &Env, stream_id: u64) -> StreamData{
-    let data: Option<Result<StreamData, _>> = env.data()
-    .get(DataKey::StreamData(stream_id));
+        verify(&env, &signature, symbol!("cancel_all"), (&id, &nonce));
+        verify_and_consume_nonce(&env, &signature, &nonce);
 
-    match data{
-        Some(Ok(stream)) => stream,
-        _ => panic_error!(&env,Error::StreamNotExist),
+        let sent = get_index(&env, DataKey::Sent(id));
+        let start = sent.len().saturating_sub(MAX_PAGE);
+
+        for stream_id in sent.slice(start..sent.len()).iter(){
+            let stream_id = stream_id.unwrap();
+            let stream = match get_active(&env, stream_id){
+                Some(stream) => stream,
+                None => continue,
+            };
+            if !stream.able_stop || stream_now(&env, &stream) > stream.stop_until{
+                continue;
+            }
+            let stream_data = get_stream_data(&env, stream_id);
+
+            let vested = get_vested(&env, stream_id, &stream, &stream_data, stream_now(&env, &stream));
+            settle(&env, stream_id, &stream, &stream_data, &vested, &stream.to);
+        }
     }
-}
+    // `stop_until` is kept, so the stream can still only be cancelled until then
+    fn en_cancel(env: Env, signature: Signature, nonce: BigInt, stream_id: u64, to_sig: Signature, to_nonce: BigInt){
+        let mut stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
 
-fn set_stream_data_cancelled(env: &Env, stream_id: u64){
-    env.data()
-    .set(DataKey::StreamData(stream_id), StreamData{
-        a_withdraw: BigInt::zero(env), //not sure if this should be the value withdrawn by the recipient. Technically, its not needed anymore, but it might be usefull.
-        cancelled: true
-    })
-}
+        let id = signature.identifier(&env);
+        let to_id = to_sig.identifier(&env);
 
-fn update_amount_withdrawn(env: &Env, stream_id: u64, total_amount_withdrawn: BigInt){
-    env.data()
-    .set(DataKey::StreamData(stream_id), StreamData{
+        // the recipient gives up their guarantee, so the creator can't do this alone
+        if id != stream.from || to_id != stream.to{
+            panic_error!(&env, Error::NotAuthorized);
+        }
+
+        if stream_data.cancelled{
+            panic_error!(&env, Error::StreamCancelled);
+        }
+
+        verify(&env, &signature, symbol!("en_cancel"), (&id, &nonce, stream_id));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+        verify(&env, &to_sig, symbol!("en_cancel"), (&to_id, &to_nonce, stream_id));
+        verify_and_consume_nonce(&env, &to_sig, &to_nonce);
+
+        stream.able_stop = true;
+        env.data().set(DataKey::Stream(stream_id), stream);
+    }
+    // `able_stop` and `stop_until` only limit the creator, the recipient can always renounce
+    fn renounce(env: Env, signature: Signature, nonce: BigInt, stream_id: u64){
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+
+        let id = signature.identifier(&env);
+
+        if id != stream.to{
+            panic_error!(&env, Error::NotAuthorized);
+        }
+
+        verify(&env, &signature, symbol!("renounce"), (&id, &nonce, id_fingerprint(&env, stream_id)));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        if stream_data.cancelled{
+            panic_error!(&env, Error::StreamCancelled);
+        }
+        if stream_data.completed{
+            panic_error!(&env, Error::StreamDone);
+        }
+
+        // the guarantee protects the recipient from the creator cancelling, not from giving the stream up themselves
+        let stream = Stream{
+            min_guar: BigInt::zero(&env),
+            ..stream
+        };
+
+        // the creator didn't cancel, so they keep the bond
+        let vested = get_vested(&env, stream_id, &stream, &stream_data, stream_now(&env, &stream));
+        settle(&env, stream_id, &stream, &stream_data, &vested, &stream.from);
+    }
+    // what `s_stream` would do now, without changing anything
+    fn prev_stop(env: Env, stream_id: u64) -> (BigInt, BigInt){
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+
+        let now = stream_now(&env, &stream);
+        if !stream.able_stop || now > stream.stop_until || stream_data.cancelled{
+            return (BigInt::zero(&env), BigInt::zero(&env));
+        }
+
+        get_split(&stream, &stream_data, &get_vested(&env, stream_id, &stream, &stream_data, now))
+    }
+    // snapshot the settlement of cancelling the stream, so that it can't change before the cancellation is executed
+    fn prep_stop(env: Env, signature: Signature, nonce: BigInt, stream_id: u64) -> (BigInt, BigInt){
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+
+        let id = signature.identifier(&env);
+
+        check_cancellable(&env, &stream, &stream_data, &id);
+
+        // a replayed signature could otherwise move the snapshot forward
+        verify(&env, &signature, symbol!("prep_stop"), (&id, &nonce, stream_id));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        let now = stream_now(&env, &stream);
+        let vested = get_vested(&env, stream_id, &stream, &stream_data, now);
+        let split = get_split(&stream, &stream_data, &vested);
+
+        env.data().set(DataKey::PendStop(stream_id), PendStop{
+            vested,
+            expires: now + STOP_WINDOW
+        });
+
+        split
+    }
+    // execute a cancellation prepared with `prep_stop`
+    fn exec_stop(env: Env, signature: Signature, nonce: BigInt, stream_id: u64){
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+
+        let id = signature.identifier(&env);
+
+        // check if creator of stream
+        if stream.from != id{
+            panic_error!(&env, Error::NotAuthorized);
+        }
+
+        // check if stream is allready cancelled
+        if stream_data.cancelled{
+            panic_error!(&env, Error::StreamCancelled);
+        }
+
+        let data: Option<Result<PendStop, _>> = env.data().get(DataKey::PendStop(stream_id));
+        let pend_stop = match data{
+            Some(Ok(pend_stop)) => pend_stop,
+            _ => panic_error!(&env, Error::NoPendingStop),
+        };
+
+        if stream_now(&env, &stream) > pend_stop.expires{
+            panic_error!(&env, Error::StopExpired);
+        }
+
+        verify(&env, &signature, symbol!("exec_stop"), (&id, &nonce, stream_id));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        env.data().remove(DataKey::PendStop(stream_id));
+        settle(&env, stream_id, &stream, &stream_data, &pend_stop.vested, &stream.to);
+    }
+    // the stream keeps vesting until `fin_stop`, so the recipient gets time to withdraw
+    fn req_stop(env: Env, signature: Signature, nonce: BigInt, stream_id: u64){
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+
+        let id = signature.identifier(&env);
+
+        check_cancellable(&env, &stream, &stream_data, &id);
+
+        verify(&env, &signature, symbol!("req_stop"), (&id, &nonce, stream_id));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        env.data().set(DataKey::StopReq(stream_id), env.ledger().timestamp());
+    }
+    // `stop_until` is only checked by `req_stop`, so a request made in time can still be finalized
+    fn fin_stop(env: Env, signature: Signature, nonce: BigInt, stream_id: u64){
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+
+        let id = signature.identifier(&env);
+
+        // check if creator of stream
+        if stream.from != id{
+            panic_error!(&env, Error::NotAuthorized);
+        }
+
+        // check if stream is allready cancelled
+        if stream_data.cancelled{
+            panic_error!(&env, Error::StreamCancelled);
+        }
+
+        let requested = match get_setting::<u64>(&env, DataKey::StopReq(stream_id)){
+            Some(requested) => requested,
+            None => panic_error!(&env, Error::NoPendingStop),
+        };
+
+        if env.ledger().timestamp() < requested.saturating_add(get_stop_delay(&env)){
+            panic_error!(&env, Error::TooEarly);
+        }
+
+        verify(&env, &signature, symbol!("fin_stop"), (&id, &nonce, stream_id));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        env.data().remove(DataKey::StopReq(stream_id));
+        let vested = get_vested(&env, stream_id, &stream, &stream_data, stream_now(&env, &stream));
+        settle(&env, stream_id, &stream, &stream_data, &vested, &stream.to);
+    }
+    // extend the stream to a later end time. The amount of the stream stays the same, so the rate drops.
+    fn e_stream(env: Env, signature: Signature, nonce: BigInt, stream_id: u64, end_time: u64){
+        check_no_milestones(&env, stream_id);
+
+        let mut stream = get_stream(&env, stream_id);
+        let mut stream_data = get_stream_data(&env, stream_id);
+
+        let id = signature.identifier(&env);
+
+        // check if creator of stream
+        if stream.from != id{
+            panic_error!(&env, Error::NotAuthorized);
+        }
+
+        // check if stream is allready cancelled
+        if stream_data.cancelled{
+            panic_error!(&env, Error::StreamCancelled);
+        }
+
+        let now = stream_now(&env, &stream);
+        // the stream can only be made longer
+        if end_time < stream.end_time || end_time < now{
+            panic_error!(&env, Error::InvalidEndTime);
+        }
+
+        // the end can be moved back by `r_stream`, a replayed signature would extend the stream again
+        verify(&env, &signature, symbol!("e_stream"), (&id, &nonce, stream_id, end_time));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        // lock in what has vested so far, so that the lower rate only applies to the future
+        rebase(&mut stream, &mut stream_data, now);
+        stream.end_time = end_time;
+
+        env.data().set(DataKey::Stream(stream_id), stream);
+        env.data().set(DataKey::StreamData(stream_id), stream_data);
+    }
+    // what has vested is locked in first, so reticking never takes away from the recipient
+    fn retick(env: Env, signature: Signature, nonce: BigInt, stream_id: u64, tick_time: u64){
+        check_no_milestones(&env, stream_id);
+
+        let mut stream = get_stream(&env, stream_id);
+        let mut stream_data = get_stream_data(&env, stream_id);
+
+        let id = signature.identifier(&env);
+
+        // check if creator of stream
+        if stream.from != id{
+            panic_error!(&env, Error::NotAuthorized);
+        }
+
+        // check if stream is allready cancelled
+        if stream_data.cancelled{
+            panic_error!(&env, Error::StreamCancelled);
+        }
+
+        verify(&env, &signature, symbol!("retick"), (&id, &nonce, stream_id, tick_time));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        let now = stream_now(&env, &stream);
+        rebase(&mut stream, &mut stream_data, now);
+
+        // a tick has to fit in what is left of the stream
+        if tick_time == 0 || tick_time > stream.end_time.saturating_sub(stream.start_time.max(now)){
+            panic_error!(&env, Error::InvalidTick);
+        }
+        // the minimum is in seconds, like in `check_time_settings` streams with `by_seq` aren't checked against it
+        let min_tick: Option<u64> = get_setting(&env, DataKey::MinTick);
+        if !stream.by_seq && matches!(min_tick, Some(min_tick) if tick_time < min_tick){
+            panic_error!(&env, Error::TickTooShort);
+        }
+        stream.tick_time = tick_time;
+
+        env.data().set(DataKey::Stream(stream_id), stream);
+        env.data().set(DataKey::StreamData(stream_id), stream_data);
+    }
+    // only the creator of a cancellable stream can reduce it, and only by what hasn't vested yet
+    fn r_stream(env: Env, signature: Signature, nonce: BigInt, stream_id: u64, reduce_by: BigInt){
+        check_no_milestones(&env, stream_id);
+
+        let mut stream = get_stream(&env, stream_id);
+        let mut stream_data = get_stream_data(&env, stream_id);
+
+        let id = signature.identifier(&env);
+
+        check_cancellable(&env, &stream, &stream_data, &id);
+
+        verify(&env, &signature, symbol!("r_stream"), (&id, &nonce, stream_id, &reduce_by));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        // lock in what has vested so far, the reduction only applies to the future
+        let now = stream_now(&env, &stream);
+        rebase(&mut stream, &mut stream_data, now);
+
+        let unvested = &stream.amount - &stream_data.a_vested;
+        if reduce_by <= BigInt::zero(&env) || reduce_by > unvested{
+            panic_error!(&env, Error::InvalidReduce);
+        }
+        if &stream.amount - &reduce_by < stream.min_guar{
+            panic_error!(&env, Error::GuaranteeViolated);
+        }
+
+        // shorten what is left of the stream by the same fraction as the unvested funds, so the rate stays the same
+        let left = &unvested - &reduce_by;
+        let duration = BigInt::from_u64(&env, stream.end_time - stream.start_time) * &left / &unvested;
+        stream.end_time = stream.start_time + duration.to_u64();
+        stream.amount = &stream.amount - &reduce_by;
+        // everything that is left might have been withdrawn already
+        stream_data.completed = stream_data.a_withdraw >= stream.amount;
+
+        env.data().set(DataKey::Stream(stream_id), stream.clone());
+        put_stream_data(&env, stream_id, &stream, stream_data.clone());
+
+        refund(&env, stream_id, &stream, &reduce_by);
+        // nothing else releases the bond once the stream is completed
+        if stream_data.completed{
+            release_bond(&env, &stream, &stream.from);
+        }
+    }
+    // the funds are pulled with the allowance of the signer and only vest from the last tick on, like after `e_stream`
+    fn add_fund(env: Env, signature: Signature, nonce: BigInt, stream_id: u64, amount: BigInt){
+        check_no_milestones(&env, stream_id);
+
+        let mut stream = get_stream(&env, stream_id);
+        let mut stream_data = get_stream_data(&env, stream_id);
+
+        let id = signature.identifier(&env);
+
+        if !stream.prefunded{
+            panic_error!(&env, Error::NotPrefunded);
+        }
+        if stream_data.cancelled{
+            panic_error!(&env, Error::StreamCancelled);
+        }
+        if amount <= BigInt::zero(&env){
+            panic_error!(&env, Error::InvalidAmount);
+        }
+
+        verify(&env, &signature, symbol!("add_fund"), (&id, &nonce, stream_id, &amount));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        // a stream that completed early already released its bond, reopening it would release it a second time
+        let now = stream_now(&env, &stream);
+        if stream_data.completed || now >= stream.end_time{
+            panic_error!(&env, Error::StreamDone);
+        }
+
+        let token = token::Client::new(&env, stream.token_c_id.clone());
+        let contract = Identifier::Contract(env.current_contract());
+        if token.allowance(&id, &contract) < amount{
+            panic_error!(&env, Error::InsufficientApproval);
+        }
+        let balance_before = token.balance(&contract);
+        token.xfer_from(&Signature::Invoker, &BigInt::zero(&env), &id, &contract, &amount);
+        if token.balance(&contract) - balance_before < amount{
+            panic_error!(&env, Error::FeeOnTransferUnsupported);
+        }
+        add_reserved(&env, &stream.token_c_id, &amount);
+
+        let mut funders = get_funders(&env, stream_id);
+        if funders.is_empty(){
+            funders.push_back((stream.from.clone(), stream.amount.clone()));
+        }
+        let mut added = false;
+        for (i, funder) in funders.clone().iter().enumerate(){
+            let (funder, contribution) = funder.unwrap();
+            if funder == id{
+                funders.set(i as u32, (funder, contribution + &amount));
+                added = true;
+            }
+        }
+        if !added{
+            funders.push_back((id, amount.clone()));
+        }
+        env.data().set(DataKey::Funders(stream_id), funders);
+
+        rebase(&mut stream, &mut stream_data, now);
+        stream.amount = &stream.amount + &amount;
+
+        put_stream_data(&env, stream_id, &stream, stream_data);
+        env.data().set(DataKey::Stream(stream_id), stream);
+    }
+    // move what is left of the stream to another token, by sending it to `swap_c_id` and calling
+    // `swap(token_in, token_out, amount_in, to) -> BigInt` on it, which has to send the new tokens to `to`.
+    // what is vested but not yet withdrawn stays vested, in the new token.
+    // callable by the admin or the creator of the stream
+    fn migrate(env: Env, signature: Signature, nonce: BigInt, stream_id: u64, token_c_id: BytesN<32>, swap_c_id: BytesN<32>){
+        check_no_milestones(&env, stream_id);
+
+        let mut stream = get_stream(&env, stream_id);
+        let mut stream_data = get_stream_data(&env, stream_id);
+
+        let id = signature.identifier(&env);
+
+        // check if creator of stream or admin. the swap decides what the recipient gets for the rest of the stream,
+        // so the creator can only use swaps that the admin allowed
+        if stream.from != id{
+            check_admin(&env, &id);
+        }else if !env.data().has(DataKey::Swap(swap_c_id.clone())){
+            panic_error!(&env, Error::SwapNotAllowed);
+        }
+
+        // check if stream is allready cancelled
+        if stream_data.cancelled{
+            panic_error!(&env, Error::StreamCancelled);
+        }
+
+        // check if all tokens have been withdrawn. everything has vested once the stream ended, so there is nothing
+        // to migrate either
+        if stream_data.completed || stream_now(&env, &stream) >= stream.end_time{
+            panic_error!(&env, Error::StreamDone);
+        }
+
+        // there is nothing in this contract to swap
+        if !stream.prefunded{
+            panic_error!(&env, Error::NotPrefunded);
+        }
+        // the bond would have to be swapped as well
+        if stream.bond != BigInt::zero(&env){
+            panic_error!(&env, Error::Bonded);
+        }
+
+        check_token_allowed(&env, &token_c_id);
+
+        // check that the signature is valid
+        verify(&env, &signature, symbol!("migrate"), (&id, &nonce, stream_id, &token_c_id, &swap_c_id));
+
+        //consume and check that nonce is valid
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        // what has vested is paid out in the old token, only the part that hasn't vested yet is swapped
+        let now = stream_now(&env, &stream);
+        if get_claimable(&env, stream_id, &stream, &stream_data, now) > BigInt::zero(&env){
+            withdraw(&env, stream_id, &stream, &stream_data);
+            stream_data = get_stream_data(&env, stream_id);
+        }
+        let remaining = &stream.amount - &stream_data.a_withdraw;
+
+        let contract = Identifier::Contract(env.current_contract());
+        let new_token = token::Client::new(&env, token_c_id.clone());
+        let balance_before = new_token.balance(&contract);
+
+        token::Client::new(&env, stream.token_c_id.clone())
+            .xfer(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Contract(swap_c_id.clone()), &remaining);
+
+        // panics, and reverts the migration, if the swap fails
+        env.invoke_contract::<BigInt>(&swap_c_id, &symbol!("swap"), vec![&env, stream.token_c_id.clone().into_val(&env), token_c_id.clone().into_val(&env), remaining.clone().into_val(&env), contract.clone().into_val(&env)]);
+
+        // don't rely on the return value of the swap contract
+        let received = new_token.balance(&contract) - balance_before;
+        if received <= BigInt::zero(&env){
+            panic_error!(&env, Error::SwapFailed);
+        }
+
+        sub_reserved(&env, &stream.token_c_id, &remaining);
+        add_reserved(&env, &token_c_id, &received);
+
+        // the stream continues with what is left, the part that has vested but isn't withdrawn yet stays vested
+        rebase(&mut stream, &mut stream_data, now);
+        stream_data.a_vested = (&stream_data.a_vested - &stream_data.a_withdraw) * &received / &remaining;
+        // what is left of the guarantee carries over at the same exchange rate
+        if stream.min_guar > stream_data.a_withdraw{
+            stream.min_guar = (&stream.min_guar - &stream_data.a_withdraw) * &received / &remaining;
+        }else{
+            stream.min_guar = BigInt::zero(&env);
+        }
+        stream_data.a_withdraw = BigInt::zero(&env);
+        stream_data.decimals = new_token.decimals();
+        stream.amount = received;
+        stream.token_c_id = token_c_id;
+
+        env.data().set(DataKey::Stream(stream_id), stream);
+        env.data().set(DataKey::StreamData(stream_id), stream_data);
+    }
+    // push the remaining funds of an ended stream to the recipient. Callable by anyone, e.g. a keeper,
+    // but only for streams that opted in with `auto_push`
+    fn finalize(env: Env, stream_id: u64){
+        check_wd_not_paused(&env);
+
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+
+        if !stream.auto_push{
+            panic_error!(&env, Error::NotAuthorized);
+        }
+
+        // check if stream has been cancelled
+        if stream_data.cancelled{
+            panic_error!(&env, Error::StreamCancelled);
+        }
+
+        // only streams that are over can be finalized
+        if stream.end_time >= stream_now(&env, &stream){
+            panic_error!(&env, Error::StreamNotEnded);
+        }
+
+        // check if all tokens have been withdrawn
+        if stream_data.completed{
+            panic_error!(&env, Error::StreamDone);
+        }
+
+        let amount_to_withdraw = &stream.amount - &stream_data.a_withdraw;
+        update_amount_withdrawn(&env, stream_id, &stream, stream.amount.clone());
+        pay_out(&env, &stream, &amount_to_withdraw);
+        release_bond(&env, &stream, &stream.from);
+        log_payout(&env, stream_id, &stream.token_c_id, &amount_to_withdraw);
+        notify_hook(&env, stream_id, &stream, &amount_to_withdraw);
+    }
+    // withdraw everything that is left of an ended stream and remove the stream from storage
+    fn cl_stream(env: Env, signature: Signature, nonce: BigInt, stream_id: u64){
+        check_wd_not_paused(&env);
+
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+
+        let id = signature.identifier(&env);
+
+        //check if user is the recipient of the stream
+        if id != stream.to{
+            panic_error!(&env, Error::NotAuthorized);
+        }
+
+        // check if stream has been cancelled
+        if stream_data.cancelled{
+            panic_error!(&env, Error::StreamCancelled);
+        }
+
+        // only streams that are over can be closed
+        if stream.end_time >= stream_now(&env, &stream){
+            panic_error!(&env, Error::StreamNotEnded);
+        }
+
+        // check that the signature is valid
+        verify(&env, &signature, symbol!("cl_stream"), (&id, &nonce, stream_id));
+
+        //consume and check that nonce is valid
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        // a repaired stream can have more withdrawn than its amount
+        let mut amount_to_withdraw = &stream.amount - &stream_data.a_withdraw;
+        if stream_data.completed || amount_to_withdraw < BigInt::zero(&env){
+            amount_to_withdraw = BigInt::zero(&env);
+        }
+
+        env.data().remove(DataKey::Stream(stream_id));
+        env.data().remove(DataKey::StreamData(stream_id));
+        if !stream_data.completed{
+            set_nb_active(&env, &stream.from, get_nb_active(&env, &stream.from).saturating_sub(1));
+        }
+
+        // don't invoke the token contract if amount == 0
+        if amount_to_withdraw != BigInt::zero(&env){
+            pay_out(&env, &stream, &amount_to_withdraw);
+            log_payout(&env, stream_id, &stream.token_c_id, &amount_to_withdraw);
+            notify_hook(&env, stream_id, &stream, &amount_to_withdraw);
+        }
+        // the bond was already returned if the stream was completed before
+        if !stream_data.completed{
+            release_bond(&env, &stream, &stream.from);
+        }
+    }
+    // until the grace is over only the recipient can claim the funds, even if the stream was cancellable.
+    // the grace is in the same unit as the times of the stream
+    fn reclaim(env: Env, signature: Signature, nonce: BigInt, stream_id: u64){
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+
+        let id = signature.identifier(&env);
+
+        // check if creator of stream
+        if stream.from != id{
+            panic_error!(&env, Error::NotAuthorized);
+        }
+
+        // check if stream is allready cancelled
+        if stream_data.cancelled{
+            panic_error!(&env, Error::StreamCancelled);
+        }
+
+        // nothing left if the recipient withdrew everything
+        if stream_data.completed{
+            panic_error!(&env, Error::StreamDone);
+        }
+
+        let grace: u64 = get_setting(&env, DataKey::RGrace).unwrap_or(RECLAIM_GRACE);
+        if stream_now(&env, &stream) <= stream.end_time.saturating_add(grace){
+            panic_error!(&env, Error::TooEarly);
+        }
+
+        verify(&env, &signature, symbol!("reclaim"), (&id, &nonce, stream_id));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        set_stream_data_cancelled(&env, stream_id, &stream);
+        refund(&env, stream_id, &stream, &(&stream.amount - &stream_data.a_withdraw));
+        release_bond(&env, &stream, &stream.from);
+    }
+    // retrieve stream and additional stream data
+    fn get_stream(env: Env, stream_id: u64) -> (Stream,StreamData){
+        (get_stream(&env, stream_id), get_stream_data(&env, stream_id))
+    }
+
+    // unlike `claimable` and `accrued` this doesn't fail before the start. for a cancelled stream the vested amount
+    // is what was paid out
+    fn summary(env: Env, stream_id: u64) -> StreamSummary{
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+        let now = stream_now(&env, &stream);
+
+        let status = if stream_data.cancelled{
+            StreamStatus::Cancelled
+        }else if stream_data.completed || stream_data.a_withdraw >= stream.amount{
+            StreamStatus::Completed
+        }else if now < stream.start_time{
+            StreamStatus::Pending
+        }else if now < stream.end_time{
+            StreamStatus::Active
+        }else{
+            StreamStatus::Ended
+        };
+
+        let (remaining, vested) = if stream_data.cancelled{
+            (BigInt::zero(&env), stream_data.a_withdraw.clone())
+        }else{
+            (&stream.amount - &stream_data.a_withdraw, get_vested(&env, stream_id, &stream, &stream_data, now))
+        };
+
+        StreamSummary{
+            claimable: get_claimable(&env, stream_id, &stream, &stream_data, now),
+            withdrawn: stream_data.a_withdraw,
+            amount: stream.amount,
+            remaining,
+            vested,
+            status,
+        }
+    }
+
+    fn get_many(env: Env, stream_ids: Vec<u64>) -> Vec<(Stream,StreamData)>{
+        let mut streams = Vec::new(&env);
+        for stream_id in stream_ids.slice(0..stream_ids.len().min(MAX_PAGE)).iter(){
+            let stream_id = stream_id.unwrap();
+            // stream data that can't be decoded is skipped as well, so that one bad id doesn't fail the whole batch
+            let stream: Option<Result<Stream, _>> = env.data().get(DataKey::Stream(stream_id));
+            let stream_data: Option<Result<StreamData, _>> = env.data().get(DataKey::StreamData(stream_id));
+            if let (Some(Ok(stream)), Some(Ok(stream_data))) = (stream, stream_data){
+                streams.push_back((stream, stream_data));
+            }
+        }
+        streams
+    }
+
+    // the stored stream changes when it is extended, reduced or migrated, so the hash only matches the current terms
+    fn chk_hash(env: Env, stream_id: u64, expected: BytesN<32>) -> bool{
+        fingerprint(&env, &get_stream(&env, stream_id)) == expected
+    }
+
+    // the stream is serialized as a map with the fields sorted by name, so the order in the struct doesn't matter
+    fn fprint(env: Env, stream: Stream) -> BytesN<32>{
+        fingerprint(&env, &stream)
+    }
+
+    fn amt_disp(env: Env, stream_id: u64) -> (BigInt, u32){
+        let stream = get_stream(&env, stream_id);
+        let decimals = token::Client::new(&env, &stream.token_c_id).decimals();
+        (stream.amount, decimals)
+    }
+
+    fn nonce(env: Env, id: Identifier) -> BigInt {
+        get_nonce(&env, &id)
+    }
+
+    fn nonces(env: Env, ids: Vec<Identifier>) -> Vec<BigInt> {
+        let mut nonces = Vec::new(&env);
+        for id in ids.iter(){
+            nonces.push_back(get_nonce(&env, &id.unwrap()));
+        }
+        nonces
+    }
+
+    // invoker signatures always use nonce 0, other signatures use the stored nonce of the signer
+    fn exp_nonce(env: Env, kind: SigKind, id: Identifier) -> BigInt {
+        match kind{
+            SigKind::Invoker => BigInt::zero(&env),
+            SigKind::Ed25519 | SigKind::Account => get_nonce(&env, &id),
+        }
+    }
+
+    fn claimable(env: Env, stream_id: u64) -> BigInt {
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+
+        check_started(&env, &stream);
+
+        get_claimable(&env, stream_id, &stream, &stream_data, stream_now(&env, &stream))
+    }
+
+    fn accrued(env: Env, stream_id: u64) -> BigInt {
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+
+        check_started(&env, &stream);
+
+        get_vested(&env, stream_id, &stream, &stream_data, stream_now(&env, &stream))
+    }
+
+    // vesting never goes down, so this is a binary search over the times of the stream, which works for every curve
+    // and for milestones. a last tick that is cut short only vests after `end_time`, in that case `end_time + 1`
+    // is returned for the full amount
+    fn vest_time(env: Env, stream_id: u64, amount: BigInt) -> u64 {
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+
+        if amount <= BigInt::zero(&env){
+            return stream.start_time;
+        }
+        if amount > stream.amount{
+            return stream.end_time;
+        }
+
+        let mut low = stream.start_time;
+        let mut high = stream.end_time.saturating_add(1);
+        while low < high{
+            let mid = low + (high - low) / 2;
+            if get_vested(&env, stream_id, &stream, &stream_data, mid) >= amount{
+                high = mid;
+            }else{
+                low = mid + 1;
+            }
+        }
+        low
+    }
+
+    // the times are capped to the stream, the end is capped to `end_time + 1` since a last tick that is cut short
+    // only vests after `end_time`. this is the schedule as it is now, cancelling the stream stops it
+    fn payout_btw(env: Env, stream_id: u64, from_time: u64, to_time: u64) -> BigInt {
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+
+        if from_time > to_time{
+            return BigInt::zero(&env);
+        }
+        let end = stream.end_time.saturating_add(1);
+        let from_time = from_time.clamp(stream.start_time, end);
+        let to_time = to_time.clamp(stream.start_time, end);
+
+        get_vested(&env, stream_id, &stream, &stream_data, to_time) - get_vested(&env, stream_id, &stream, &stream_data, from_time)
+    }
+
+    // the guarantee of the recipient is taken into account, so this is `amount - accrued` only without one
+    fn refundable(env: Env, stream_id: u64) -> BigInt {
+        let (refund, _) = Self::prev_stop(env, stream_id);
+        refund
+    }
+
+    // only the checks on who is calling and the state of the stream, pauses and the withdrawal interval are not
+    // taken into account
+    fn can_wd(env: Env, stream_id: u64, id: Identifier) -> bool {
+        if !env.data().has(DataKey::Stream(stream_id)) || !env.data().has(DataKey::StreamData(stream_id)){
+            return false;
+        }
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+
+        if id != stream.to && Some(Ok(id)) != env.data().get(DataKey::Delegate(stream_id)){
+            return false;
+        }
+        !stream_data.cancelled && !stream_data.completed && stream_data.a_withdraw < stream.amount
+    }
+
+    // same checks as `check_cancellable`
+    fn can_stop(env: Env, stream_id: u64, id: Identifier) -> bool {
+        if !env.data().has(DataKey::Stream(stream_id)) || !env.data().has(DataKey::StreamData(stream_id)){
+            return false;
+        }
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+
+        stream.from == id && stream.able_stop && stream_now(&env, &stream) <= stream.stop_until && !stream_data.cancelled
+    }
+
+    // a tick is `tick_time` seconds, or ledgers for streams with `by_seq`, so with a `tick_time` of 1 this is the rate
+    // per second. extending or reducing a stream changes the rate of the ticks that are left, and with `Curve::FrontLoad`
+    // every tick releases less than the one before
+    fn rate(env: Env, stream_id: u64) -> BigInt {
+        check_no_milestones(&env, stream_id);
+
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+
+        // `r_stream` can end a stream at its start, it has no ticks left then
+        let now = stream_now(&env, &stream);
+        if now >= stream.end_time || stream.end_time <= stream.start_time{
+            return BigInt::zero(&env);
+        }
+
+        let tick = now.saturating_sub(stream.start_time) / stream.tick_time;
+        let tick_start = stream.start_time + tick * stream.tick_time;
+        vested_at(&stream, &stream_data, tick_start + stream.tick_time) - vested_at(&stream, &stream_data, tick_start)
+    }
+
+    // before the start the next tick is the first one and the index is 0. once the stream ended this returns
+    // (0, 0, number of ticks). ticks are counted from `start_time`, which moves when the stream is changed
+    fn timing(env: Env, stream_id: u64) -> (u64, u64, u64) {
+        let stream = get_stream(&env, stream_id);
+        let now = stream_now(&env, &stream);
+
+        if now >= stream.end_time{
+            return (0, 0, get_total_ticks(&stream));
+        }
+
+        let tick = now.saturating_sub(stream.start_time) / stream.tick_time;
+        let next_tick = (stream.start_time + (tick + 1) * stream.tick_time).min(stream.end_time);
+        (next_tick - now, stream.end_time - now, tick)
+    }
+
+    // kept for callers of the old view, nothing is held back for after the end anymore
+    fn dust(env: Env, stream_id: u64) -> BigInt {
+        get_stream(&env, stream_id);
+        BigInt::zero(&env)
+    }
+
+    // like `claimable`, but after extending the stream like `e_stream` would
+    fn claim_ext(env: Env, stream_id: u64, end_time: u64) -> BigInt {
+        check_no_milestones(&env, stream_id);
+
+        let mut stream = get_stream(&env, stream_id);
+        let mut stream_data = get_stream_data(&env, stream_id);
+
+        let now = stream_now(&env, &stream);
+        if end_time < stream.end_time || end_time < now{
+            panic_error!(&env, Error::InvalidEndTime);
+        }
+
+        rebase(&mut stream, &mut stream_data, now);
+        stream.end_time = end_time;
+
+        get_claimable(&env, stream_id, &stream, &stream_data, now)
+    }
+
+    // find streams with stream data that doesn't decode, e.g. because a raw BigInt was stored instead of `StreamData`
+    fn corrupted(env: Env, start_id: u64, limit: u64) -> Vec<u64> {
+        let end_id = get_stream_id(&env).min(start_id.saturating_add(limit));
+
+        let mut corrupted = Vec::new(&env);
+        for stream_id in start_id..end_id{
+            let data: Option<Result<StreamData, _>> = env.data()
+                .get(DataKey::StreamData(stream_id));
+
+            if let Some(Err(_)) = data{
+                corrupted.push_back(stream_id);
+            }
+        }
+        corrupted
+    }
+
+    // for looking into entries that `corrupted` reports
+    fn raw_data(env: Env, signature: Signature, stream_id: u64) -> Option<Bytes>{
+        let id = signature.identifier(&env);
+        check_admin(&env, &id);
+
+        // dont need nonce, nothing is changed
+        verify(&env, &signature, symbol!("raw_data"), (&id, stream_id));
+
+        let data: Option<Result<RawVal, _>> = env.data().get(DataKey::StreamData(stream_id));
+        data.map(|data| data.unwrap().serialize(&env))
+    }
+
+    fn my_streams(env: Env, id: Identifier) -> Vec<u64>{
+        let sent = get_index(&env, DataKey::Sent(id.clone()));
+        let received = get_index(&env, DataKey::Received(id));
+
+        // both indices are sorted, so merge them and drop the streams to self that are in both
+        let mut streams = Vec::new(&env);
+        let (mut i, mut j) = (0, 0);
+        while i < sent.len() || j < received.len(){
+            let next = if j == received.len(){
+                sent.get_unchecked(i).unwrap()
+            }else if i == sent.len(){
+                received.get_unchecked(j).unwrap()
+            }else{
+                sent.get_unchecked(i).unwrap().min(received.get_unchecked(j).unwrap())
+            };
+
+            if i < sent.len() && sent.get_unchecked(i).unwrap() == next{
+                i += 1;
+            }
+            if j < received.len() && received.get_unchecked(j).unwrap() == next{
+                j += 1;
+            }
+            streams.push_back(next);
+        }
+        streams
+    }
+
+    fn between(env: Env, from: Identifier, to: Identifier) -> Vec<u64>{
+        get_index(&env, pair_key(&env, &from, &to))
+    }
+
+    // every stream is loaded, so only the last `MAX_PAGE` streams the identity received are looked at
+    fn active_in(env: Env, to: Identifier) -> Vec<u64>{
+        let received = get_index(&env, DataKey::Received(to));
+        let start = received.len().saturating_sub(MAX_PAGE);
+
+        let mut active = Vec::new(&env);
+        for stream_id in received.slice(start..received.len()).iter(){
+            let stream_id = stream_id.unwrap();
+            if get_active(&env, stream_id).is_some(){
+                active.push_back(stream_id);
+            }
+        }
+        active
+    }
+
+    // like `sent_page` at most `MAX_PAGE` streams are looked at. payouts are logged in time order, so the history of a
+    // stream is only read up to `to_time`
+    fn income(env: Env, to: Identifier, token_c_id: BytesN<32>, from_time: u64, to_time: u64, start: u32, limit: u32) -> BigInt{
+        let mut income = BigInt::zero(&env);
+        let received = get_index(&env, DataKey::Received(to));
+        if start >= received.len(){
+            return income;
+        }
+
+        let end = start.saturating_add(limit.min(MAX_PAGE)).min(received.len());
+        for stream_id in received.slice(start..end).iter(){
+            for payout in get_history(&env, stream_id.unwrap()).iter(){
+                let payout = payout.unwrap();
+                if payout.time >= to_time{
+                    break;
+                }
+                if payout.token_c_id == token_c_id && from_time <= payout.time{
+                    income += payout.amount;
+                }
+            }
+        }
+        income
+    }
+
+    fn sent_page(env: Env, from: Identifier, start: u32, limit: u32) -> Vec<u64>{
+        let sent = get_index(&env, DataKey::Sent(from));
+        if start >= sent.len(){
+            return Vec::new(&env);
+        }
+
+        let end = start.saturating_add(limit.min(MAX_PAGE)).min(sent.len());
+        sent.slice(start..end)
+    }
+
+    // at most `MAX_PAGE` ids and buckets, like `corrupted` the ids are paged by range
+    fn ladder(env: Env, token_c_id: BytesN<32>, bucket_size: u64, nb_buckets: u32, start_id: u64, limit: u64) -> Vec<BigInt>{
+        let nb_buckets = nb_buckets.min(MAX_PAGE);
+        let end_id = get_stream_id(&env).min(start_id.saturating_add(limit.min(MAX_PAGE as u64)));
+
+        let mut ladder = Vec::new(&env);
+        for _ in 0..nb_buckets{
+            ladder.push_back(BigInt::zero(&env));
+        }
+
+        for stream_id in start_id..end_id{
+            // closed streams are removed, and corrupted ones are skipped
+            let stream: Option<Result<Stream, _>> = env.data().get(DataKey::Stream(stream_id));
+            let stream_data: Option<Result<StreamData, _>> = env.data().get(DataKey::StreamData(stream_id));
+            let (stream, stream_data) = match (stream, stream_data){
+                (Some(Ok(stream)), Some(Ok(stream_data))) if stream.token_c_id == token_c_id => (stream, stream_data),
+                _ => continue,
+            };
+            if stream_data.cancelled{
+                continue;
+            }
+
+            // only streams with milestones need storage reads to compute what has vested
+            let has_milestones = env.data().has(DataKey::Milestones(stream_id));
+            let vested_by = |time| if has_milestones{
+                get_vested(&env, stream_id, &stream, &stream_data, time)
+            }else{
+                vested_at(&stream, &stream_data, time)
+            };
+
+            // for streams driven by the ledger sequence, the buckets are `bucket_size` ledgers long
+            let now = stream_now(&env, &stream);
+            let mut vested = vested_by(now);
+            for bucket in 0..nb_buckets{
+                let bucket_end = now.saturating_add(bucket_size.saturating_mul(bucket as u64 + 1));
+                let vested_end = vested_by(bucket_end);
+                ladder.set(bucket, ladder.get_unchecked(bucket).unwrap() + &vested_end - &vested);
+                vested = vested_end;
+            }
+        }
+        ladder
+    }
+
+    fn nb_recips(env: Env, from: Identifier) -> u32{
+        let mut recipients: Vec<Identifier> = Vec::new(&env);
+        for stream in get_active_sent(&env, &from).iter(){
+            let stream = stream.unwrap();
+            if !recipients.contains(&stream.to){
+                recipients.push_back(stream.to);
+            }
+        }
+        recipients.len()
+    }
+
+    fn avg_end(env: Env, from: Identifier, token_c_id: BytesN<32>) -> u64{
+        let mut weighted = BigInt::zero(&env);
+        let mut total = BigInt::zero(&env);
+        for stream in get_active_sent(&env, &from).iter(){
+            let stream = stream.unwrap();
+            if stream.token_c_id == token_c_id{
+                weighted += &stream.amount * stream.end_time;
+                total += &stream.amount;
+            }
+        }
+
+        if total == BigInt::zero(&env){
+            return 0;
+        }
+        (weighted / total).to_u64()
+    }
+
+    fn allow(env: Env, signature: Signature, nonce: BigInt, token_c_id: BytesN<32>){
+        let id = signature.identifier(&env);
+        check_admin(&env, &id);
+
+        verify(&env, &signature, symbol!("allow"), (&id, &nonce, &token_c_id));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        let key = DataKey::Allowed(token_c_id);
+        if !env.data().has(key.clone()){
+            env.data().set(key, ());
+            env.data().set(DataKey::NbAllowed, get_nb_allowed(&env) + 1);
+        }
+    }
+
+    fn disallow(env: Env, signature: Signature, nonce: BigInt, token_c_id: BytesN<32>){
+        let id = signature.identifier(&env);
+        check_admin(&env, &id);
+
+        verify(&env, &signature, symbol!("disallow"), (&id, &nonce, &token_c_id));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        let key = DataKey::Allowed(token_c_id);
+        if env.data().has(key.clone()){
+            env.data().remove(key);
+            env.data().set(DataKey::NbAllowed, get_nb_allowed(&env) - 1);
+        }
+    }
+
+    fn set_grace(env: Env, signature: Signature, nonce: BigInt, enabled: bool, grace: u64){
+        let id = signature.identifier(&env);
+        check_admin(&env, &id);
+
+        verify(&env, &signature, symbol!("set_grace"), (&id, &nonce, enabled, grace));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        if enabled{
+            env.data().set(DataKey::StartGrace, grace);
+        }else{
+            env.data().remove(DataKey::StartGrace);
+        }
+    }
+
+    fn set_dtick(env: Env, signature: Signature, nonce: BigInt, tick_time: Option<u64>){
+        let id = signature.identifier(&env);
+        check_admin(&env, &id);
+
+        verify(&env, &signature, symbol!("set_dtick"), (&id, &nonce, tick_time));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        match tick_time{
+            Some(0) => panic_error!(&env, Error::InvalidTick),
+            Some(tick_time) => env.data().set(DataKey::DefTick, tick_time),
+            None => env.data().remove(DataKey::DefTick),
+        }
+    }
+
+    fn set_mins(env: Env, signature: Signature, nonce: BigInt, duration: Option<u64>, tick_time: Option<u64>){
+        let id = signature.identifier(&env);
+        check_admin(&env, &id);
+
+        verify(&env, &signature, symbol!("set_mins"), (&id, &nonce, duration, tick_time));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        match duration{
+            Some(duration) => env.data().set(DataKey::MinDur, duration),
+            None => env.data().remove(DataKey::MinDur),
+        }
+        match tick_time{
+            Some(tick_time) => env.data().set(DataKey::MinTick, tick_time),
+            None => env.data().remove(DataKey::MinTick),
+        }
+    }
+
+    fn set_max(env: Env, signature: Signature, nonce: BigInt, max: Option<u32>){
+        let id = signature.identifier(&env);
+        check_admin(&env, &id);
+
+        verify(&env, &signature, symbol!("set_max"), (&id, &nonce, max));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        match max{
+            Some(max) => env.data().set(DataKey::MaxStreams, max),
+            None => env.data().remove(DataKey::MaxStreams),
+        }
+    }
+
+    fn allow_swap(env: Env, signature: Signature, nonce: BigInt, swap_c_id: BytesN<32>, allowed: bool){
+        let id = signature.identifier(&env);
+        check_admin(&env, &id);
+
+        verify(&env, &signature, symbol!("allow_swap"), (&id, &nonce, &swap_c_id, allowed));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        if allowed{
+            env.data().set(DataKey::Swap(swap_c_id), ());
+        }else{
+            env.data().remove(DataKey::Swap(swap_c_id));
+        }
+    }
+
+    fn set_hook(env: Env, signature: Signature, nonce: BigInt, hook: Option<BytesN<32>>){
+        let id = signature.identifier(&env);
+        check_admin(&env, &id);
+
+        verify(&env, &signature, symbol!("set_hook"), (&id, &nonce, &hook));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        match hook{
+            Some(hook) => env.data().set(DataKey::Hook, hook),
+            None => env.data().remove(DataKey::Hook),
+        }
+    }
+
+    fn set_wpause(env: Env, signature: Signature, nonce: BigInt, paused: bool){
+        let id = signature.identifier(&env);
+        check_admin(&env, &id);
+
+        verify(&env, &signature, symbol!("set_wpause"), (&id, &nonce, paused));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        if paused{
+            env.data().set(DataKey::WPaused, true);
+        }else{
+            env.data().remove(DataKey::WPaused);
+        }
+    }
+
+    fn set_sdelay(env: Env, signature: Signature, nonce: BigInt, delay: Option<u64>){
+        let id = signature.identifier(&env);
+        check_admin(&env, &id);
+
+        verify(&env, &signature, symbol!("set_sdelay"), (&id, &nonce, delay));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        match delay{
+            Some(delay) => env.data().set(DataKey::StopDelay, delay),
+            None => env.data().remove(DataKey::StopDelay),
+        }
+    }
+
+    fn set_rgrace(env: Env, signature: Signature, nonce: BigInt, grace: Option<u64>){
+        let id = signature.identifier(&env);
+        check_admin(&env, &id);
+
+        verify(&env, &signature, symbol!("set_rgrace"), (&id, &nonce, grace));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        match grace{
+            Some(grace) => env.data().set(DataKey::RGrace, grace),
+            None => env.data().remove(DataKey::RGrace),
+        }
+    }
+
+    // there is no way back, a wind down can only be started once
+    fn wind_down(env: Env, signature: Signature, nonce: BigInt){
+        let id = signature.identifier(&env);
+        check_admin(&env, &id);
+
+        if env.data().has(DataKey::WindDown){
+            panic_error!(&env, Error::WindingDown);
+        }
+
+        verify(&env, &signature, symbol!("wind_down"), (&id, &nonce));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        env.data().set(DataKey::WindDown, env.ledger().timestamp() + WIND_DELAY);
+    }
+
+    // anyone can settle, so that funds don't stay locked if the parties of a stream are gone
+    fn wd_settle(env: Env, stream_id: u64){
+        let settle_after: Option<Result<u64, _>> = env.data().get(DataKey::WindDown);
+        match settle_after{
+            Some(Ok(settle_after)) if settle_after <= env.ledger().timestamp() => {},
+            _ => panic_error!(&env, Error::NotWoundDown),
+        }
+
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+
+        if stream_data.cancelled{
+            panic_error!(&env, Error::StreamCancelled);
+        }
+
+        // nobody cancelled the stream, so the bond goes back to the creator
+        let vested = get_vested(&env, stream_id, &stream, &stream_data, stream_now(&env, &stream));
+        settle(&env, stream_id, &stream, &stream_data, &vested, &stream.from);
+    }
+
+    fn reconcile(env: Env, signature: Signature, nonce: BigInt, stream_id: u64) -> bool{
+        let id = signature.identifier(&env);
+        check_admin(&env, &id);
+
+        verify(&env, &signature, symbol!("reconcile"), (&id, &nonce, stream_id));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        let stream = get_stream(&env, stream_id);
+        let stream_data = get_stream_data(&env, stream_id);
+
+        if stream_data.cancelled{
+            panic_error!(&env, Error::StreamCancelled);
+        }
+
+        // the contract doesn't hold anything for streams that aren't prefunded
+        if !stream.prefunded{
+            return false;
+        }
+
+        let token = token::Client::new(&env, stream.token_c_id.clone());
+        let mut owed = &stream.amount - &stream_data.a_withdraw;
+        if !stream_data.completed{
+            owed += &stream.bond;
+        }
+        // only the part of the balance that isn't reserved for the other streams of the token backs this stream
+        let mut others = get_reserved(&env, &stream.token_c_id) - &owed;
+        if others < BigInt::zero(&env){
+            others = BigInt::zero(&env);
+        }
+        let mut balance = token.balance(&Identifier::Contract(env.current_contract())) - others;
+        if balance < BigInt::zero(&env){
+            balance = BigInt::zero(&env);
+        }
+        if balance >= owed{
+            return false;
+        }
+
+        // the recipient is paid what has vested first, the creator gets what is left of the balance after that
+        let claimable = get_claimable(&env, stream_id, &stream, &stream_data, stream_now(&env, &stream));
+        let payout = if claimable < balance { claimable } else { balance.clone() };
+        let refund = balance - &payout;
+
+        if payout != BigInt::zero(&env){
+            token.xfer(&Signature::Invoker, &BigInt::zero(&env), &stream.to, &payout);
+            update_amount_withdrawn(&env, stream_id, &stream, &stream_data.a_withdraw + &payout);
+            log_payout(&env, stream_id, &stream.token_c_id, &payout);
+        }
+        if refund != BigInt::zero(&env){
+            token.xfer(&Signature::Invoker, &BigInt::zero(&env), &stream.from, &refund);
+            env.data().set(DataKey::Refunded(stream.token_c_id.clone()), get_refunded(&env, &stream.token_c_id) + &refund);
+        }
+
+        // nothing more is owed by this stream, including the part that was taken away
+        sub_reserved(&env, &stream.token_c_id, &owed);
+        set_stream_data_cancelled(&env, stream_id, &stream);
+        true
+    }
+
+    // the admin has to look up `a_withdraw` and `cancelled`, e.g. from the transfers of the token.
+    // only stream data that doesn't decode can be overwritten
+    fn repair(env: Env, signature: Signature, nonce: BigInt, stream_id: u64, a_withdraw: BigInt, cancelled: bool){
+        let id = signature.identifier(&env);
+        check_admin(&env, &id);
+
+        verify(&env, &signature, symbol!("repair"), (&id, &nonce, stream_id, &a_withdraw, cancelled));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        let stream = get_stream(&env, stream_id);
+        let data: Option<Result<StreamData, _>> = env.data().get(DataKey::StreamData(stream_id));
+        if let Some(Ok(_)) = data{
+            panic_error!(&env, Error::NotCorrupted);
+        }
+
+        put_stream_data(&env, stream_id, &stream, StreamData{
+            completed: a_withdraw >= stream.amount,
+            a_withdraw,
+            cancelled,
+            a_vested: BigInt::zero(&env),
+            decimals: token::Client::new(&env, &stream.token_c_id).decimals(),
+            last_wd: 0,
+        });
+    }
+
+    // send dust and tokens that were sent to the contract by accident to `dest`. Funds of streams are never touched.
+    fn sweep(env: Env, signature: Signature, nonce: BigInt, token_c_id: BytesN<32>, dest: Identifier){
+        let id = signature.identifier(&env);
+        check_admin(&env, &id);
+
+        verify(&env, &signature, symbol!("sweep"), (&id, &nonce, &token_c_id, &dest));
+        verify_and_consume_nonce(&env, &signature, &nonce);
+
+        let (_, free) = Self::breakdown(env.clone(), token_c_id.clone());
+
+        // don't invoke the token contract if there is nothing to sweep
+        if free > BigInt::zero(&env){
+            token::Client::new(&env, token_c_id)
+                .xfer(&Signature::Invoker, &BigInt::zero(&env), &dest, &free);
+        }
+    }
+
+    // reserved is what the contract owes to streams, free is the rest of the balance of the contract.
+    // if the contract holds less than it owes, free is 0 and a `shortfall` event with the token and the missing
+    // amount is published
+    fn breakdown(env: Env, token_c_id: BytesN<32>) -> (BigInt, BigInt){
+        let reserved = get_reserved(&env, &token_c_id);
+        let balance = token::Client::new(&env, token_c_id.clone())
+            .balance(&Identifier::Contract(env.current_contract()));
+
+        let free = balance - &reserved;
+        if free < BigInt::zero(&env){
+            publish_event(&env, symbol!("shortfall"), (token_c_id, -free));
+            return (reserved, BigInt::zero(&env));
+        }
+        (reserved, free)
+    }
+
+    fn refunded(env: Env, token_c_id: BytesN<32>) -> BigInt{
+        get_refunded(&env, &token_c_id)
+    }
+
+    // the reserved amount is kept up to date on every transfer out of a stream, so it already is the locked value
+    fn tvl(env: Env, token_c_id: BytesN<32>) -> BigInt{
+        get_reserved(&env, &token_c_id)
+    }
+}
+fn get_setting<T: soroban_sdk::TryFromVal<Env, soroban_sdk::RawVal>>(env: &Env, key: DataKey) -> Option<T>
+where T::Error: core::fmt::Debug{
+    match env.data().get(key){
+        Some(Ok(value)) => Some(value),
+        _ => None,
+    }
+}
+
+fn get_stop_delay(env: &Env) -> u64{
+    get_setting(env, DataKey::StopDelay).unwrap_or(STOP_DELAY)
+}
+
+// panics if `id` is not the admin of the contract
+fn check_admin(env: &Env, id: &Identifier){
+    let admin: Option<Result<Identifier, _>> = env.data().get(DataKey::Admin);
+
+    match admin{
+        Some(Ok(admin)) if &admin == id => (),
+        _ => panic_error!(env, Error::NotAuthorized),
+    }
+}
+
+// panics if there is a whitelist and the token is not on it
+fn check_token_allowed(env: &Env, token_c_id: &BytesN<32>){
+    if get_nb_allowed(env) > 0 && !env.data().has(DataKey::Allowed(token_c_id.clone())){
+        panic_error!(env, Error::TokenNotAllowed);
+    }
+}
+
+fn get_nb_allowed(env: &Env) -> u32 {
+    env.data()
+        .get(DataKey::NbAllowed)
+        .unwrap_or(Ok(0u32))
+        .unwrap()
+}
+
+fn get_reserved(env: &Env, token_c_id: &BytesN<32>) -> BigInt {
+    env.data()
+        .get(DataKey::Reserved(token_c_id.clone()))
+        .unwrap_or_else(|| Ok(BigInt::zero(env)))
+        .unwrap()
+}
+
+fn add_reserved(env: &Env, token_c_id: &BytesN<32>, amount: &BigInt){
+    env.data().set(DataKey::Reserved(token_c_id.clone()), get_reserved(env, token_c_id) + amount);
+}
+
+fn sub_reserved(env: &Env, token_c_id: &BytesN<32>, amount: &BigInt){
+    env.data().set(DataKey::Reserved(token_c_id.clone()), get_reserved(env, token_c_id) - amount);
+}
+
+// check a stream that vests by timestamp against the minimums and the start grace set by the admin
+fn check_time_settings(env: &Env, stream: &Stream){
+    // keep tiny streams from filling up storage
+    let min_duration: Option<Result<u64, _>> = env.data().get(DataKey::MinDur);
+    if let Some(Ok(min_duration)) = min_duration{
+        if stream.end_time.saturating_sub(stream.start_time) < min_duration{
+            panic_error!(env, Error::DurationTooShort);
+        }
+    }
+    let min_tick: Option<Result<u64, _>> = env.data().get(DataKey::MinTick);
+    if let Some(Ok(min_tick)) = min_tick{
+        if stream.tick_time < min_tick{
+            panic_error!(env, Error::TickTooShort);
+        }
+    }
+
+    // streams starting in the past are only rejected if the admin enabled it, so that backfilling keeps working by default.
+    // what vested before now can be withdrawn right away, with a grace only streams that start at most `grace` ago can do that
+    let grace: Option<Result<u64, _>> = env.data().get(DataKey::StartGrace);
+    if let Some(Ok(grace)) = grace{
+        if stream.start_time.saturating_add(grace) < env.ledger().timestamp(){
+            panic_error!(env, Error::StartTimeInPast);
+        }
+    }
+}
+
+// lock the funds of the stream in this contract and store it.
+// With `Signature::Invoker` as `xfer_sig` the funds are pulled from `stream.from` using the allowance of this contract,
+// otherwise `xfer_sig` has to be a signature of `stream.from` for the `xfer` of the token contract.
+// returns the id of the created stream
+fn create_stream(env: &Env, mut stream: Stream, xfer_sig: &Signature, xfer_nonce: &BigInt) -> u64 {
+    if env.data().has(DataKey::WindDown){
+        panic_error!(env, Error::WindingDown);
+    }
+
+    check_token_allowed(env, &stream.token_c_id);
+
+    stream.tick_time = resolve_tick(env, stream.tick_time);
+
+    // the settings below are in seconds, streams with `by_seq` count ledgers so they aren't checked against them
+    if !stream.by_seq{
+        check_time_settings(env, &stream);
+    }
+
+    // probe the token before anything is stored or pulled, so that a contract that isn't a token fails with our own error
+    let decimals = match env.try_invoke_contract::<u32, Status>(&stream.token_c_id, &symbol!("decimals"), Vec::new(env)){
+        Ok(Ok(decimals)) => decimals,
+        _ => panic_error!(env, Error::InvalidToken),
+    };
+    let token = token::Client::new(env, stream.token_c_id.clone());
+    // streams that aren't prefunded are paid from the balance of the creator as they go, only the bond is locked
+    let locked = if stream.prefunded { &stream.amount + &stream.bond } else { stream.bond.clone() };
+    if locked != BigInt::zero(env){
+        let contract = soroban_auth::Identifier::Contract(env.current_contract());
+        let balance_before = token.balance(&contract);
+        match xfer_sig{
+            Signature::Invoker => {
+                // fail with our own error instead of the one of the token contract
+                if token.allowance(&stream.from, &contract) < locked{
+                    panic_error!(env, Error::InsufficientApproval);
+                }
+                token.xfer_from(&soroban_auth::Signature::Invoker, &BigInt::from_u32(env, 0),&stream.from ,&contract, &locked);
+            }
+            Signature::Ed25519(_) | Signature::Account(_) => {
+                // the funds have to come from the creator of the stream
+                if xfer_sig.identifier(env) != stream.from{
+                    panic_error!(env, Error::NotAuthorized);
+                }
+                token.xfer(xfer_sig, xfer_nonce, &contract, &locked);
+            }
+        }
+
+        // tokens that charge a fee on transfers aren't supported, the stream would promise more than the contract holds.
+        // such streams are rejected instead of being reduced to what was received, so the creator doesn't end up with
+        // a different stream than the one they signed
+        if token.balance(&contract) - balance_before < locked{
+            panic_error!(env, Error::FeeOnTransferUnsupported);
+        }
+
+        add_reserved(env, &stream.token_c_id, &locked);
+    }
+
+    store_stream(env, stream, decimals)
+}
+
+// a tick_time of 0 means the default set by the admin
+fn resolve_tick(env: &Env, tick_time: u64) -> u64{
+    if tick_time != 0{
+        return tick_time;
+    }
+    match get_setting(env, DataKey::DefTick){
+        Some(tick_time) => tick_time,
+        None => panic_error!(env, Error::InvalidTick),
+    }
+}
+
+// `total` split by the weights of `recipients`, rounded down. what is lost to rounding is added to the first share
+fn split_by_weight(env: &Env, total: &BigInt, recipients: &Vec<(Identifier, u32)>, total_weight: u64) -> Vec<BigInt>{
+    let mut shares = Vec::new(env);
+    let mut remainder = total.clone();
+    for recipient in recipients.iter(){
+        let (_, weight) = recipient.unwrap();
+        let share = total * weight / total_weight;
+        remainder -= &share;
+        shares.push_back(share);
+    }
+    if let Some(Ok(first)) = shares.first(){
+        shares.set(0, first + remainder);
+    }
+    shares
+}
+
+// store a new stream that is already funded, and add it to the indices
+// returns the id of the stream
+// streams created by `split_to` count towards the cap of the sender as well
+fn store_stream(env: &Env, stream: Stream, decimals: u32) -> u64 {
+    let max: Option<Result<u32, _>> = env.data().get(DataKey::MaxStreams);
+    if let Some(Ok(max)) = max{
+        if get_nb_active(env, &stream.from) >= max{
+            panic_error!(env, Error::TooManyStreams);
+        }
+    }
+
+    let stream_id = get_and_inc_stream_id(env);
+
+    add_to_index(env, DataKey::Sent(stream.from.clone()), stream_id);
+    add_to_index(env, DataKey::Received(stream.to.clone()), stream_id);
+    add_to_index(env, pair_key(env, &stream.from, &stream.to), stream_id);
+
+    // store stream
+    env.data()
+    .set(DataKey::Stream(stream_id),stream.clone());
+
+    // store mutable stream data
+    put_stream_data(env, stream_id, &stream, StreamData{
+        a_withdraw: BigInt::zero(env),
+        cancelled: false,
+        a_vested: BigInt::zero(env),
+        decimals,
+        last_wd: 0,
+        completed: false,
+    });
+
+    //return stream id
+    stream_id
+}
+
+// every event is published with the topics (stream, name, version), so indexers can tell schema versions apart
+fn publish_event<D: IntoVal<Env, RawVal>>(env: &Env, name: Symbol, data: D){
+    env.events().publish((symbol!("stream"), name, EVENT_VERSION), data);
+}
+
+// send what has vested and wasn't withdrawn yet to the recipient
+fn withdraw(env: &Env, stream_id: u64, stream: &Stream, stream_data: &StreamData){
+    // get the amount of funds that we can withdraw minus the amount we have allready withdrawn
+    let amount_to_withdraw = get_claimable(env, stream_id, stream, stream_data, stream_now(env, stream));
+
+    // don't invoke the token contract if amount == 0. this doesn't fail, so a relayer that submits a withdrawal
+    // in the same tick as another one isn't punished for it, but the event shows that nothing was paid
+    if amount_to_withdraw == BigInt::zero(env) {
+        publish_event(env, symbol!("no_wdraw"), stream_id);
+        return;
+    }
+
+    // store the withdrawal before calling the token contract, so that a token that calls back into this
+    // contract can't withdraw the same funds again
+    let a_withdraw = &stream_data.a_withdraw + &amount_to_withdraw;
+    let completed = a_withdraw >= stream.amount;
+    put_stream_data(env, stream_id, stream, StreamData{
+        completed,
+        a_withdraw,
+        last_wd: env.ledger().timestamp(),
+        ..stream_data.clone()
+    });
+
+    pay_out(env, stream, &amount_to_withdraw);
+    if completed{
+        release_bond(env, stream, &stream.from);
+    }
+    log_payout(env, stream_id, &stream.token_c_id, &amount_to_withdraw);
+    notify_hook(env, stream_id, stream, &amount_to_withdraw);
+}
+
+// send `amount` of the stream to the recipient. streams that aren't prefunded pull it from the creator,
+// which reverts the whole call if their balance or the allowance of this contract is too low
+fn pay_out(env: &Env, stream: &Stream, amount: &BigInt){
+    let token = token::Client::new(env, stream.token_c_id.clone());
+    if stream.prefunded{
+        token.xfer(&Signature::Invoker, &BigInt::zero(env), &stream.to, amount);
+        sub_reserved(env, &stream.token_c_id, amount);
+    }else{
+        token.xfer_from(&Signature::Invoker, &BigInt::zero(env), &stream.from, &stream.to, amount);
+    }
+}
+
+// send the bond of the stream to `to`, once the stream is cancelled or completed
+fn release_bond(env: &Env, stream: &Stream, to: &Identifier){
+    if stream.bond != BigInt::zero(env){
+        token::Client::new(env, stream.token_c_id.clone())
+            .xfer(&Signature::Invoker, &BigInt::zero(env), to, &stream.bond);
+        sub_reserved(env, &stream.token_c_id, &stream.bond);
+    }
+}
+
+// give `amount` of the stream back to the creator, or to everyone who funded it if it was topped up with `add_fund`.
+// the creator still holds the funds of streams that aren't prefunded
+fn refund(env: &Env, stream_id: u64, stream: &Stream, amount: &BigInt){
+    if stream.prefunded{
+        let token = token::Client::new(env, stream.token_c_id.clone());
+        let funders = get_funders(env, stream_id);
+        if funders.is_empty(){
+            token.xfer(&Signature::Invoker, &BigInt::zero(env), &stream.from, amount);
+        }else{
+            // split by contribution, what is lost to rounding goes to the creator
+            let mut total = BigInt::zero(env);
+            for funder in funders.iter(){
+                total += funder.unwrap().1;
+            }
+            let mut left = amount.clone();
+            for funder in funders.iter().skip(1){
+                let (funder, contribution) = funder.unwrap();
+                let share = amount * &contribution / &total;
+                if share != BigInt::zero(env){
+                    token.xfer(&Signature::Invoker, &BigInt::zero(env), &funder, &share);
+                    left -= share;
+                }
+            }
+            if left != BigInt::zero(env){
+                token.xfer(&Signature::Invoker, &BigInt::zero(env), &stream.from, &left);
+            }
+        }
+        sub_reserved(env, &stream.token_c_id, amount);
+        env.data().set(DataKey::Refunded(stream.token_c_id.clone()), get_refunded(env, &stream.token_c_id) + amount);
+    }
+}
+
+fn get_funders(env: &Env, stream_id: u64) -> Vec<(Identifier, BigInt)>{
+    env.data()
+        .get(DataKey::Funders(stream_id))
+        .unwrap_or_else(|| Ok(Vec::new(env)))
+        .unwrap()
+}
+
+fn get_history(env: &Env, stream_id: u64) -> Vec<Payout>{
+    env.data()
+        .get(DataKey::History(stream_id))
+        .unwrap_or_else(|| Ok(Vec::new(env)))
+        .unwrap()
+}
+
+// the history is kept when a stream is closed
+fn log_payout(env: &Env, stream_id: u64, token_c_id: &BytesN<32>, amount: &BigInt){
+    let mut history = get_history(env, stream_id);
+    history.push_back(Payout{
+        time: env.ledger().timestamp(),
+        amount: amount.clone(),
+        token_c_id: token_c_id.clone(),
+    });
+    env.data().set(DataKey::History(stream_id), history);
+}
+
+// call `on_wdraw(stream_id, amount)` on the hook contract, if there is one, and `on_recv(stream_id, amount)` on the
+// recipient if it opted in with `notify_to`.
+// the calls are made with `try_invoke_contract`, so a failing hook or recipient doesn't roll back the withdrawal
+fn notify_hook(env: &Env, stream_id: u64, stream: &Stream, amount: &BigInt){
+    let hook: Option<Result<BytesN<32>, _>> = env.data().get(DataKey::Hook);
+    if let Some(Ok(hook)) = hook{
+        let _ = env.try_invoke_contract::<(), Status>(&hook, &symbol!("on_wdraw"), vec![env, stream_id.into_val(env), amount.clone().into_val(env)]);
+    }
+    if let (true, Identifier::Contract(to)) = (stream.notify_to, &stream.to){
+        let _ = env.try_invoke_contract::<(), Status>(to, &symbol!("on_recv"), vec![env, stream_id.into_val(env), amount.clone().into_val(env)]);
+    }
+}
+
+fn get_operators(env: &Env, funder: &Identifier) -> Vec<Identifier>{
+    env.data()
+        .get(DataKey::Operators(funder.clone()))
+        .unwrap_or_else(|| Ok(Vec::new(env)))
+        .unwrap()
+}
+
+// panics if `id` can't create streams funded by `funder`
+fn check_funder(env: &Env, id: &Identifier, funder: &Identifier){
+    if id != funder && !get_operators(env, funder).contains(id){
+        panic_error!(env, Error::NotAuthorized);
+    }
+}
+
+fn check_wd_not_paused(env: &Env){
+    if env.data().has(DataKey::WPaused){
+        panic_error!(env, Error::WithdrawPaused);
+    }
+}
+
+fn get_index(env: &Env, key: DataKey) -> Vec<u64>{
+    env.data()
+        .get(key)
+        .unwrap_or_else(|| Ok(Vec::new(env)))
+        .unwrap()
+}
+
+fn add_to_index(env: &Env, key: DataKey, stream_id: u64){
+    let mut index = get_index(env, key.clone());
+    index.push_back(stream_id);
+    env.data().set(key, index);
+}
+
+fn pair_key(env: &Env, from: &Identifier, to: &Identifier) -> DataKey{
+    DataKey::Pair(env.compute_hash_sha256(&(from.clone(), to.clone()).serialize(env)))
+}
+
+// streams created by `from` that are neither cancelled nor fully withdrawn
+fn get_active_sent(env: &Env, from: &Identifier) -> Vec<Stream>{
+    let mut active = Vec::new(env);
+    for stream_id in get_index(env, DataKey::Sent(from.clone())).iter(){
+        if let Some(stream) = get_active(env, stream_id.unwrap()){
+            active.push_back(stream);
+        }
+    }
+    active
+}
+
+// store the data of a stream, keeping the number of active streams of the sender up to date. stream data that
+// doesn't decode is counted as active, like it was when the stream was created
+fn put_stream_data(env: &Env, stream_id: u64, stream: &Stream, stream_data: StreamData){
+    let old: Option<Result<StreamData, _>> = env.data().get(DataKey::StreamData(stream_id));
+    let was_active = match old{
+        Some(Ok(old)) => !old.cancelled && !old.completed,
+        Some(Err(_)) => true,
+        None => false,
+    };
+    let is_active = !stream_data.cancelled && !stream_data.completed;
+    env.data().set(DataKey::StreamData(stream_id), stream_data);
+
+    let nb_active = get_nb_active(env, &stream.from);
+    if is_active && !was_active{
+        set_nb_active(env, &stream.from, nb_active + 1);
+    }else if was_active && !is_active{
+        set_nb_active(env, &stream.from, nb_active.saturating_sub(1));
+    }
+}
+
+fn get_nb_active(env: &Env, from: &Identifier) -> u32{
+    get_setting(env, DataKey::NbActive(from.clone())).unwrap_or(0)
+}
+
+fn set_nb_active(env: &Env, from: &Identifier, nb_active: u32){
+    env.data().set(DataKey::NbActive(from.clone()), nb_active);
+}
+
+// the stream, if it is neither cancelled nor fully withdrawn
+fn get_active(env: &Env, stream_id: u64) -> Option<Stream>{
+    // closed streams are removed. streams whose data can't be decoded are skipped, they have to be repaired first
+    let stream: Option<Result<Stream, _>> = env.data().get(DataKey::Stream(stream_id));
+    let stream_data: Option<Result<StreamData, _>> = env.data().get(DataKey::StreamData(stream_id));
+    if let (Some(Ok(stream)), Some(Ok(stream_data))) = (stream, stream_data){
+        if !stream_data.cancelled && !stream_data.completed{
+            return Some(stream);
+        }
+    }
+    None
+}
+
+fn get_refunded(env: &Env, token_c_id: &BytesN<32>) -> BigInt {
+    env.data()
+        .get(DataKey::Refunded(token_c_id.clone()))
+        .unwrap_or_else(|| Ok(BigInt::zero(env)))
+        .unwrap()
+}
+
+// `c_stream`, `w_stream` and `s_stream` sign over (identifier, nonce, fingerprint), where the fingerprint is the
+// sha256 of the serialized `Stream` when creating and of the serialized stream id otherwise
+fn fingerprint(env: &Env, stream: &Stream) -> BytesN<32>{
+    env.compute_hash_sha256(&stream.clone().serialize(env))
+}
+
+fn id_fingerprint(env: &Env, stream_id: u64) -> BytesN<32>{
+    env.compute_hash_sha256(&stream_id.serialize(env))
+}
+
+fn get_stream_id(env: &Env) -> u64 {
+    env
+        .data()
+        .get(DataKey::StreamId)
+        .unwrap_or(Ok(0u64))
+        .unwrap()
+}
+
+fn get_and_inc_stream_id(env: &Env) -> u64 {
+    let prev = get_stream_id(env);
+
+    env.data().set(DataKey::StreamId, prev + 1);
+    prev
+}
+
+fn get_stream(env: &Env, stream_id: u64) -> Stream{
+    let data: Option<Result<Stream, _>> = env.data()
+        .get(DataKey::Stream(stream_id));
+    
+    match data{
+        Some(Ok(stream)) => stream,
+        _ => panic_error!(&env,Error::StreamNotExist),
+    }
+}
+
+fn get_stream_data(env: &Env, stream_id: u64) -> StreamData{
+    let data: Option<Result<StreamData, _>> = env.data()
+    .get(DataKey::StreamData(stream_id));
+
+    match data{
+        Some(Ok(stream)) => stream,
+        _ => panic_error!(&env,Error::StreamNotExist),
+    }
+}
+
+// the current time on the clock of the stream, a timestamp or the ledger sequence number
+fn stream_now(env: &Env, stream: &Stream) -> u64{
+    if stream.by_seq{
+        env.ledger().sequence() as u64
+    }else{
+        env.ledger().timestamp()
+    }
+}
+
+fn get_total_ticks(stream: &Stream) -> u64{
+    // stream duration
+    let duration = stream.end_time - stream.start_time;
+
+    let mut total_ticks = duration / stream.tick_time;
+    // round up the total ticks, the last tick is cut short by the end of the stream.
+    // a stream shorter than `tick_time` has a single tick, so everything is paid out in one lump after `end_time`
+    if !duration.is_multiple_of(stream.tick_time){
+        total_ticks += 1;
+    }
+    total_ticks
+}
+
+// the amount of the stream that has vested at `time`. all tick math goes through here:
+// before the start only `a_vested` has vested and after the end everything has
+fn vested_at(stream: &Stream, stream_data: &StreamData, time: u64) -> BigInt{
+    // if we are over the end of the stream, then everything has vested.
+    if stream.end_time < time{
+        return stream.amount.clone();
+    }
+
+    // nothing new vests before the start of the stream
+    if time <= stream.start_time{
+        return stream_data.a_vested.clone();
+    }
+
+    let time_elapsed = time - stream.start_time;
+    // elsapsed ticks
+    let total_ticks = get_total_ticks(stream);
+    let elapsed_ticks = (time_elapsed / stream.tick_time).min(total_ticks);
+
+    // only the funds that haven't vested before `start_time` are spread over the ticks.
+    // multiply before dividing, so that rounding never loses more than 1 and the last tick vests everything
+    let unvested = &stream.amount - &stream_data.a_vested;
+    let vested = match stream.curve{
+        Curve::Linear => unvested * elapsed_ticks / total_ticks,
+        // k * (2n - k) / n^2, which is n^2 / n^2 at the last tick. 2n - k is split up so that it can't overflow
+        Curve::FrontLoad => {
+            let scaled = unvested * elapsed_ticks;
+            (scaled.clone() * total_ticks + scaled * (total_ticks - elapsed_ticks)) / total_ticks / total_ticks
+        }
+    };
+    vested + &stream_data.a_vested
+}
+
+// the amount of the stream that has vested at `time`, following the milestones if the stream has them
+fn get_vested(env: &Env, stream_id: u64, stream: &Stream, stream_data: &StreamData, time: u64) -> BigInt{
+    let milestones: Vec<(u64, BigInt)> = match get_setting(env, DataKey::Milestones(stream_id)){
+        Some(milestones) => milestones,
+        None => return vested_at(stream, stream_data, time),
+    };
+
+    if stream.end_time < time{
+        return stream.amount.clone();
+    }
+
+    // the amount of the last milestone that is unlocked
+    let mut vested = BigInt::zero(env);
+    for milestone in milestones.iter(){
+        let (unlock_time, amount) = milestone.unwrap();
+        if unlock_time > time{
+            break;
+        }
+        vested = amount;
+    }
+    vested
+}
+
+// panics if the milestones aren't ordered or don't add up to the amount of the stream
+fn check_milestones(env: &Env, stream: &Stream, milestones: &Vec<(u64, BigInt)>){
+    let mut last: Option<(u64, BigInt)> = None;
+    for milestone in milestones.iter(){
+        let (unlock_time, amount) = milestone.unwrap();
+        let ordered = match &last{
+            Some((last_time, last_amount)) => unlock_time > *last_time && &amount >= last_amount,
+            None => amount >= BigInt::zero(env),
+        };
+        if !ordered || unlock_time > stream.end_time{
+            panic_error!(env, Error::InvalidMilestones);
+        }
+        last = Some((unlock_time, amount));
+    }
+
+    match last{
+        Some((_, amount)) if amount == stream.amount => (),
+        _ => panic_error!(env, Error::InvalidMilestones),
+    }
+}
+
+// panics if the stream releases milestones, which can't be rebased
+fn check_no_milestones(env: &Env, stream_id: u64){
+    if env.data().has(DataKey::Milestones(stream_id)){
+        panic_error!(env, Error::HasMilestones);
+    }
+}
+
+// the amount the recipient can withdraw at `time`
+fn get_claimable(env: &Env, stream_id: u64, stream: &Stream, stream_data: &StreamData, time: u64) -> BigInt{
+    if stream_data.cancelled{
+        return BigInt::zero(env);
+    }
+    // the recipient might have withdrawn more than has vested if the settlement was prepared earlier
+    let claimable = get_vested(env, stream_id, stream, stream_data, time) - &stream_data.a_withdraw;
+    if claimable < BigInt::zero(env){
+        return BigInt::zero(env);
+    }
+    claimable
+}
+
+// lock in what has vested at `now` in `a_vested` and restart the schedule at the last tick before `now`,
+// so that changes to the stream only apply to the future
+fn rebase(stream: &mut Stream, stream_data: &mut StreamData, now: u64){
+    if now > stream.start_time{
+        let elapsed_ticks = (now - stream.start_time) / stream.tick_time;
+        stream_data.a_vested = vested_at(stream, stream_data, now);
+        stream.start_time += elapsed_ticks * stream.tick_time;
+    }
+}
+
+// panics before the start of the stream. at `start_time` itself nothing has vested yet, but it doesn't fail
+fn check_started(env: &Env, stream: &Stream){
+    if stream_now(env, stream) < stream.start_time{
+        panic_error!(env, Error::StreamNotStarted);
+    }
+}
+
+// panics if the stream can't be cancelled by `id`
+fn check_cancellable(env: &Env, stream: &Stream, stream_data: &StreamData, id: &Identifier){
+    // check if creator of stream
+    if &stream.from != id{
+        panic_error!(env, Error::NotAuthorized);
+    }
+
+    // check if stream is cancellable
+    if !stream.able_stop || stream_now(env, stream) > stream.stop_until{
+        panic_error!(env, Error::StreamNotCancellable);
+    }
+    // check if stream is allready cancelled
+    if stream_data.cancelled{
+        panic_error!(env, Error::StreamCancelled);
+    }
+}
+
+// (refund to the creator, payout to the recipient) when cancelling a stream of which `vested` has vested
+fn get_split(stream: &Stream, stream_data: &StreamData, vested: &BigInt) -> (BigInt, BigInt){
+    // the guaranteed amount always goes to the recipient
+    let vested = if vested < &stream.min_guar{
+        if stream.min_guar < stream.amount { &stream.min_guar } else { &stream.amount }
+    }else{
+        vested
+    };
+
+    // the recipient might have withdrawn more than `vested` if the settlement was prepared earlier
+    if &stream_data.a_withdraw > vested{
+        return (&stream.amount - &stream_data.a_withdraw, BigInt::zero(stream.amount.env()));
+    }
+
+    (&stream.amount - vested, vested - &stream_data.a_withdraw)
+}
+
+// cancel the stream, paying out what has vested to the recipient and sending the rest back to the creator.
+// the bond goes to `bond_to`, unless it was returned when the stream was completed
+fn settle(env: &Env, stream_id: u64, stream: &Stream, stream_data: &StreamData, vested: &BigInt, bond_to: &Identifier){
+    let (to_refund, payout) = get_split(stream, stream_data, vested);
+
+    // the stream is cancelled before calling the token contract, like in `withdraw`
+    let a_withdraw = &stream_data.a_withdraw + &payout;
+    put_stream_data(env, stream_id, stream, StreamData{
+        completed: a_withdraw >= stream.amount,
+        a_withdraw,
+        cancelled: true,
+        ..stream_data.clone()
+    });
+
+    // don't invoke the token contract if amount == 0
+    if payout != BigInt::zero(env){
+        pay_out(env, stream, &payout);
+        log_payout(env, stream_id, &stream.token_c_id, &payout);
+    }
+    if to_refund != BigInt::zero(env){
+        refund(env, stream_id, stream, &to_refund);
+    }
+    if !stream_data.completed{
+        release_bond(env, stream, bond_to);
+    }
+}
+
+fn set_stream_data_cancelled(env: &Env, stream_id: u64, stream: &Stream){
+    let stream_data = get_stream_data(env, stream_id);
+    // keep a_withdraw, so that the amount withdrawn by the recipient is still known after cancelling
+    put_stream_data(env, stream_id, stream, StreamData{
+        cancelled: true,
+        ..stream_data
+    })
+}
+
+fn update_amount_withdrawn(env: &Env, stream_id: u64, stream: &Stream, total_amount_withdrawn: BigInt){
+    let stream_data = get_stream_data(env, stream_id);
+    put_stream_data(env, stream_id, stream, StreamData{
+        completed: total_amount_withdrawn >= stream.amount,
         a_withdraw: total_amount_withdrawn,
-        cancelled: false
+        ..stream_data
     });
 }
 
+// Invoker signatures always use nonce 0: the invocation itself is the authorization, so there is no signed
+// payload that could be replayed off-chain. A nonzero nonce is rejected with `IncorrectNonceForInvoker`.
+// Ed25519 and account signatures have to use the stored nonce of the signer, which is then incremented,
+// a reused nonce is rejected with `IncorrectNonce`.
+// The nonce is only written after it was checked, and if the invocation fails afterwards the increment is
+// rolled back together with the rest of the invocation.
 fn verify_and_consume_nonce(env: &Env, sig: &Signature, nonce: &BigInt) {
     match sig {
         Signature::Invoker => {
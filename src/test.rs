@@ -1,7 +1,7 @@
-use soroban_auth::{Identifier, Signature};
-use soroban_sdk::{Env, AccountId, BytesN, IntoVal, testutils::{Accounts, Ledger, LedgerInfo}, BigInt};
+use soroban_auth::{Identifier, Signature, testutils::ed25519};
+use soroban_sdk::{Env, AccountId, BytesN, IntoVal, testutils::{Accounts, Events, Ledger, LedgerInfo, ContractFunctionSet}, BigInt, Symbol, RawVal, TryIntoVal, symbol, vec, contractimpl, serde::Serialize};
 
-use crate::{token::{self, TokenMetadata}, StreamingContract, StreamingContractClient, Stream};
+use crate::{token::{self, TokenMetadata}, StreamingContract, StreamingContractClient, Stream, StreamData, DataKey, SigKind, Curve, StreamStatus, RateArgs, vested_at, id_fingerprint};
 
 fn create_token_contract(e: &Env, admin: &AccountId) -> (BytesN<32>, token::Client) {
     let id = e.register_contract_token(None);
@@ -15,16 +15,223 @@ fn create_token_contract(e: &Env, admin: &AccountId) -> (BytesN<32>, token::Clie
             decimals: 7,
         },
     );
-    (id.into(), token)
+    (id, token)
 }
 
 fn create_streaming_contract(e: &Env) -> (BytesN<32>, StreamingContractClient){
     
     let contract_id = e.register_contract(None, StreamingContract);
 
-    let streaming_contract = StreamingContractClient::new(&e,&contract_id);
+    let streaming_contract = StreamingContractClient::new(e,&contract_id);
 
-    (contract_id.into(),streaming_contract)
+    (contract_id,streaming_contract)
+}
+
+// creates the token and streaming contracts, and funds user_1 with 1000 tokens that the streaming contract may spend
+fn setup(e: &Env) -> (AccountId, AccountId, BytesN<32>, token::Client, BytesN<32>, StreamingContractClient){
+    let user_1 = e.accounts().generate();
+    let user_2 = e.accounts().generate();
+
+    let (token_contract_id, token_client) = create_token_contract(e, &user_1);
+
+    let (streaming_contract_id, stream_client) = create_streaming_contract(e);
+
+    token_client.with_source_account(&user_1)
+    .mint(&Signature::Invoker, &BigInt::zero(e), &Identifier::Account(user_1.clone()), &BigInt::from_u64(e,1000));
+
+    token_client.with_source_account(&user_1)
+    .approve(&Signature::Invoker, &BigInt::zero(e), &Identifier::Contract(streaming_contract_id.clone()), &BigInt::from_u64(e,1000));
+
+    (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client)
+}
+
+fn new_stream(e: &Env, from: &AccountId, to: &AccountId, token_c_id: &BytesN<32>, amount: u64, start_time: u64, end_time: u64) -> Stream{
+    Stream{
+        from: Identifier::Account(from.clone()),
+        to: Identifier::Account(to.clone()),
+        amount: BigInt::from_u64(e,amount),
+        start_time,
+        end_time,
+        tick_time: 1,
+        token_c_id: token_c_id.clone(),
+        able_stop: false,
+        stop_until: u64::MAX,
+        auto_push: false,
+        min_guar: BigInt::zero(e),
+        prefunded: true,
+        min_wd_int: 0,
+        by_seq: false,
+        bond: BigInt::zero(e),
+        curve: Curve::Linear,
+        notify_to: false,
+    }
+}
+
+// the streaming contract, with an extra `corrupt` function that overwrites the stream data of a stream with a raw BigInt
+struct CorruptibleContract;
+
+impl ContractFunctionSet for CorruptibleContract{
+    fn call(&self, func: &Symbol, env: Env, args: &[RawVal]) -> Option<RawVal>{
+        if func == &symbol!("corrupt"){
+            let stream_id: u64 = args[0].try_into_val(&env).unwrap();
+            env.data().set(DataKey::StreamData(stream_id), BigInt::from_u32(&env, 1));
+            return Some(().into_val(&env));
+        }
+        StreamingContract.call(func, env, args)
+    }
+}
+
+fn create_corruptible_streaming_contract(e: &Env) -> (BytesN<32>, StreamingContractClient){
+    let contract_id = e.register_contract(None, CorruptibleContract);
+
+    let streaming_contract = StreamingContractClient::new(e,&contract_id);
+
+    (contract_id,streaming_contract)
+}
+
+fn corrupt(e: &Env, contract_id: &BytesN<32>, stream_id: u64){
+    e.invoke_contract::<()>(contract_id, &symbol!("corrupt"), vec![e, stream_id.into_val(e)]);
+}
+
+// swaps any token for twice the amount of the token it holds
+pub struct MockSwap;
+
+#[contractimpl]
+impl MockSwap{
+    pub fn swap(env: Env, _token_in: BytesN<32>, token_out: BytesN<32>, amount_in: BigInt, to: Identifier) -> BigInt{
+        let amount_out = amount_in * 2u32;
+        token::Client::new(&env, token_out)
+            .xfer(&Signature::Invoker, &BigInt::zero(&env), &to, &amount_out);
+        amount_out
+    }
+}
+
+// remembers the last withdrawal it was notified of
+pub struct TestHook;
+
+#[contractimpl]
+impl TestHook{
+    pub fn on_wdraw(env: Env, stream_id: u64, amount: BigInt){
+        env.data().set(symbol!("last"), (stream_id, amount));
+    }
+
+    pub fn last(env: Env) -> (u64, BigInt){
+        env.data().get_unchecked(symbol!("last")).unwrap()
+    }
+}
+
+// vault that records what it received through `on_recv`
+pub struct TestVault;
+
+#[contractimpl]
+impl TestVault{
+    pub fn on_recv(env: Env, stream_id: u64, amount: BigInt){
+        let total: BigInt = env.data().get(symbol!("total")).unwrap_or_else(|| Ok(BigInt::zero(&env))).unwrap();
+        env.data().set(symbol!("total"), total + &amount);
+        env.data().set(symbol!("last"), (stream_id, amount));
+    }
+
+    pub fn total(env: Env) -> BigInt{
+        env.data().get(symbol!("total")).unwrap_or_else(|| Ok(BigInt::zero(&env))).unwrap()
+    }
+}
+
+// token that calls `w_stream` again from within `xfer`, and remembers whether that worked
+pub struct ReentrantToken;
+
+#[contractimpl]
+impl ReentrantToken{
+    pub fn target(env: Env, streaming_c_id: BytesN<32>, stream_id: u64){
+        env.data().set(symbol!("target"), (streaming_c_id, stream_id));
+    }
+
+    pub fn reentered(env: Env) -> bool{
+        env.data().get(symbol!("reentered")).unwrap_or(Ok(false)).unwrap()
+    }
+
+    pub fn decimals(_env: Env) -> u32{
+        7
+    }
+
+    pub fn allowance(env: Env, _from: Identifier, _spender: Identifier) -> BigInt{
+        BigInt::from_u32(&env, 1000)
+    }
+
+    pub fn balance(env: Env, _id: Identifier) -> BigInt{
+        env.data().get(symbol!("balance")).unwrap_or(Ok(BigInt::zero(&env))).unwrap()
+    }
+
+    pub fn xfer_from(env: Env, _sig: Signature, _nonce: BigInt, _from: Identifier, _to: Identifier, amount: BigInt){
+        env.data().set(symbol!("balance"), Self::balance(env.clone(), Identifier::Contract(env.current_contract())) + amount);
+    }
+
+    pub fn xfer(env: Env, _sig: Signature, _nonce: BigInt, _to: Identifier, _amount: BigInt){
+        let (streaming_c_id, stream_id): (BytesN<32>, u64) = env.data().get_unchecked(symbol!("target")).unwrap();
+        let result = env.try_invoke_contract::<(), soroban_sdk::Status>(&streaming_c_id, &symbol!("w_stream"), vec![
+            &env,
+            Signature::Invoker.into_val(&env),
+            BigInt::zero(&env).into_val(&env),
+            stream_id.into_val(&env),
+        ]);
+        env.data().set(symbol!("reentered"), matches!(result, Ok(Ok(()))));
+    }
+}
+
+// token that keeps 1% of every `xfer_from` as a fee
+// in its own module, since the generated functions would clash with the ones of `ReentrantToken`
+mod fee_token{
+    use super::*;
+
+    pub struct FeeToken;
+
+    #[contractimpl]
+    impl FeeToken{
+        pub fn decimals(_env: Env) -> u32{
+            7
+        }
+
+        pub fn allowance(env: Env, _from: Identifier, _spender: Identifier) -> BigInt{
+            BigInt::from_u32(&env, 1000)
+        }
+
+        pub fn balance(env: Env, _id: Identifier) -> BigInt{
+            env.data().get(symbol!("balance")).unwrap_or(Ok(BigInt::zero(&env))).unwrap()
+        }
+
+        pub fn xfer_from(env: Env, _sig: Signature, _nonce: BigInt, _from: Identifier, _to: Identifier, amount: BigInt){
+            let received = amount.clone() - amount / 100;
+            env.data().set(symbol!("balance"), Self::balance(env.clone(), Identifier::Contract(env.current_contract())) + received);
+        }
+    }
+}
+use fee_token::FeeToken;
+
+// the contract has to hold exactly what it still owes on `stream_ids`, which have to be all streams of the token.
+// closed streams are skipped, they don't hold any funds
+fn assert_invariants(e: &Env, stream_client: &StreamingContractClient, streaming_contract_id: &BytesN<32>, token_c_id: &BytesN<32>, stream_ids: &[u64]){
+    let mut owed = BigInt::zero(e);
+    for stream in stream_client.get_many(&soroban_sdk::Vec::from_slice(e, stream_ids)).iter(){
+        let (stream, stream_data) = stream.unwrap();
+        if stream_data.cancelled || stream_data.completed{
+            continue;
+        }
+        if stream.prefunded{
+            owed += &stream.amount - &stream_data.a_withdraw;
+        }
+        owed += stream.bond;
+    }
+    assert_eq!(stream_client.tvl(token_c_id), owed);
+    assert_eq!(token::Client::new(e, token_c_id).balance(&Identifier::Contract(streaming_contract_id.clone())), owed);
+}
+
+fn set_timestamp(e: &Env, timestamp: u64){
+    e.ledger().set(LedgerInfo {
+        timestamp,
+        protocol_version: 1,
+        sequence_number: 1,
+        network_passphrase: Default::default(),
+        base_reserve: 1,
+    });
 }
 
 #[test]
@@ -53,13 +260,23 @@ fn test(){
         tick_time: 1,
         token_c_id: token_contract_id.clone(),
         able_stop: false,
+        stop_until: u64::MAX,
+        auto_push: false,
+        min_guar: BigInt::zero(&env),
+        prefunded: true,
+        min_wd_int: 0,
+        by_seq: false,
+        bond: BigInt::zero(&env),
+        curve: Curve::Linear,
+        notify_to: false,
     };
 
     let stream_id = stream_client
     .with_source_account(&user_1)
     .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
     
-    assert_eq!(BigInt::from_u64(&env,10),token_client.balance(&soroban_auth::Identifier::Contract(streaming_contract_id)));
+    assert_eq!(BigInt::from_u64(&env,10),token_client.balance(&soroban_auth::Identifier::Contract(streaming_contract_id.clone())));
+    assert_invariants(&env, &stream_client, &streaming_contract_id, &token_contract_id, &[stream_id]);
 
     env.ledger().set(LedgerInfo {
         timestamp: env.ledger().timestamp() + 5,
@@ -74,10 +291,4251 @@ fn test(){
     .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
 
     assert_eq!(BigInt::from_u32(&env, 5),token_client.balance(&Identifier::Account(user_2.clone())));
+    assert_invariants(&env, &stream_client, &streaming_contract_id, &token_contract_id, &[stream_id]);
 
     stream_client.with_source_account(&user_2)
     .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
 
     assert_eq!(BigInt::from_u32(&env, 5),token_client.balance(&Identifier::Account(user_2)));
+    assert_invariants(&env, &stream_client, &streaming_contract_id, &token_contract_id, &[stream_id]);
+
+}
+
+#[test]
+fn test_extend(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // 10 per tick, 50 vested
+    set_timestamp(&env, start + 5);
+
+    stream_client.with_source_account(&user_1)
+    .e_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &(start + 15));
+
+    let (stream, stream_data) = stream_client.get_stream(&stream_id);
+    assert_eq!(stream.amount, BigInt::from_u64(&env, 100));
+    assert_eq!(stream.end_time, start + 15);
+    assert_eq!(stream_data.a_vested, BigInt::from_u64(&env, 50));
+
+    // vested amount didn't shrink
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(BigInt::from_u32(&env, 50),token_client.balance(&Identifier::Account(user_2.clone())));
+
+    // the remaining 50 are spread over 10 ticks, so 5 per tick
+    set_timestamp(&env, start + 7);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(BigInt::from_u32(&env, 60),token_client.balance(&Identifier::Account(user_2.clone())));
+
+    // the total stays the same
+    set_timestamp(&env, start + 16);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(BigInt::from_u32(&env, 100),token_client.balance(&Identifier::Account(user_2)));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(8))")]
+fn test_extend_earlier(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_1)
+    .e_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &(start + 9));
+}
+
+#[test]
+fn test_corrupted(){
+    let env = Env::default();
+
+    let user_1 = env.accounts().generate();
+    let user_2 = env.accounts().generate();
+
+    let (token_contract_id, token_client) = create_token_contract(&env, &user_1);
+
+    let (streaming_contract_id, stream_client) = create_corruptible_streaming_contract(&env);
+
+    token_client.with_source_account(&user_1)
+    .mint(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Account(user_1.clone()), &BigInt::from_u64(&env,1000));
+
+    token_client.with_source_account(&user_1)
+    .approve(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Contract(streaming_contract_id.clone()), &BigInt::from_u64(&env,1000));
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+
+    for _ in 0..4{
+        stream_client.with_source_account(&user_1)
+        .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    }
+
+    assert!(stream_client.corrupted(&0, &10).is_empty());
+
+    corrupt(&env, &streaming_contract_id, 1);
+    corrupt(&env, &streaming_contract_id, 3);
+
+    assert_eq!(stream_client.corrupted(&0, &10), vec![&env, 1, 3]);
+    // pages
+    assert_eq!(stream_client.corrupted(&0, &2), vec![&env, 1]);
+    assert_eq!(stream_client.corrupted(&2, &2), vec![&env, 3]);
+    assert!(stream_client.corrupted(&4, &2).is_empty());
+
+    // listings skip the corrupted streams instead of failing
+    let streams = stream_client.get_many(&vec![&env, 0, 1, 2, 3]);
+    assert_eq!(streams.len(), 2);
+    assert_eq!(stream_client.active_in(&Identifier::Account(user_2.clone())), vec![&env, 0, 2]);
+    stream_client.with_source_account(&user_1)
+    .cancel_all(&Signature::Invoker, &BigInt::zero(&env));
+}
+
+#[test]
+fn test_cancel_keeps_withdrawn(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 3);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_invariants(&env, &stream_client, &streaming_contract_id, &token_contract_id, &[stream_id]);
+
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    let (_, stream_data) = stream_client.get_stream(&stream_id);
+    assert!(stream_data.cancelled);
+    assert_eq!(stream_data.a_withdraw, BigInt::from_u32(&env, 30));
+    assert_eq!(token_client.balance(&Identifier::Account(user_1)), BigInt::from_u32(&env, 970));
+    assert_invariants(&env, &stream_client, &streaming_contract_id, &token_contract_id, &[stream_id]);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(1))")]
+fn test_close(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 3);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    set_timestamp(&env, start + 11);
+    stream_client.with_source_account(&user_2)
+    .cl_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 100));
+    assert_eq!(token_client.balance(&Identifier::Contract(streaming_contract_id)), BigInt::zero(&env));
+
+    stream_client.get_stream(&stream_id);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(9))")]
+fn test_close_not_ended(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 10);
+    stream_client.with_source_account(&user_2)
+    .cl_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+}
+
+#[test]
+fn test_cancel_in_grace(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    stream.stop_until = start + 5;
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 5);
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    // the recipient gets what has vested
+    assert_eq!(token_client.balance(&Identifier::Account(user_1)), BigInt::from_u32(&env, 950));
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 50));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(6))")]
+fn test_cancel_after_grace(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    stream.stop_until = start + 5;
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 6);
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+}
+
+#[test]
+fn test_allowed_token(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .allow(&Signature::Invoker, &BigInt::zero(&env), &token_contract_id);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    assert_eq!(token_client.balance(&Identifier::Contract(streaming_contract_id)), BigInt::from_u32(&env, 100));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(11))")]
+fn test_disallowed_token(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let (other_token_id, _) = create_token_contract(&env, &user_1);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .allow(&Signature::Invoker, &BigInt::zero(&env), &other_token_id);
+    stream_client.with_source_account(&user_1)
+    .allow(&Signature::Invoker, &BigInt::zero(&env), &token_contract_id);
+    stream_client.with_source_account(&user_1)
+    .disallow(&Signature::Invoker, &BigInt::zero(&env), &token_contract_id);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(2))")]
+fn test_allow_not_admin(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1));
+    stream_client.with_source_account(&user_2)
+    .allow(&Signature::Invoker, &BigInt::zero(&env), &token_contract_id);
+}
+
+#[test]
+fn test_breakdown(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // tokens sent directly to the contract are not reserved
+    token_client.with_source_account(&user_1)
+    .xfer(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Contract(streaming_contract_id), &BigInt::from_u32(&env, 7));
 
-}
\ No newline at end of file
+    assert_eq!(stream_client.breakdown(&token_contract_id), (BigInt::from_u32(&env, 100), BigInt::from_u32(&env, 7)));
+
+    set_timestamp(&env, start + 4);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    assert_eq!(stream_client.breakdown(&token_contract_id), (BigInt::from_u32(&env, 60), BigInt::from_u32(&env, 7)));
+}
+
+#[test]
+fn test_breakdown_shortfall(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10));
+
+    // the token admin takes 60 away from the contract
+    token_client.with_source_account(&user_1)
+    .burn(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Contract(streaming_contract_id.clone()), &BigInt::from_u32(&env, 60));
+
+    assert_eq!(stream_client.breakdown(&token_contract_id), (BigInt::from_u32(&env, 100), BigInt::zero(&env)));
+
+    let (contract_id, topics, data) = env.events().all().last().unwrap().unwrap();
+    assert_eq!(contract_id, streaming_contract_id);
+    assert_eq!(topics, vec![&env, symbol!("stream").into_val(&env), symbol!("shortfall").into_val(&env), 1u32.into_val(&env)]);
+    let (token_c_id, missing): (BytesN<32>, BigInt) = data.try_into_val(&env).unwrap();
+    assert_eq!(token_c_id, token_contract_id);
+    assert_eq!(missing, 60);
+}
+
+#[test]
+fn test_prepared_cancel(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 100);
+    stream.able_stop = true;
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 20);
+    let split = stream_client.with_source_account(&user_1)
+    .prep_stop(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(split, (BigInt::from_u32(&env, 80), BigInt::from_u32(&env, 20)));
+
+    // more vests, but the settlement doesn't change
+    set_timestamp(&env, start + 50);
+    stream_client.with_source_account(&user_1)
+    .exec_stop(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    assert_eq!(token_client.balance(&Identifier::Account(user_1)), BigInt::from_u32(&env, 980));
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 20));
+    assert_eq!(token_client.balance(&Identifier::Contract(streaming_contract_id)), BigInt::zero(&env));
+    assert!(stream_client.get_stream(&stream_id).1.cancelled);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(13))")]
+fn test_prepared_cancel_expired(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 100);
+    stream.able_stop = true;
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_1)
+    .prep_stop(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    set_timestamp(&env, start + 61);
+    stream_client.with_source_account(&user_1)
+    .exec_stop(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+}
+
+#[test]
+fn test_prepared_cancel_by_seq(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+
+    let set_ledger = |timestamp, sequence_number| env.ledger().set(LedgerInfo {
+        timestamp,
+        protocol_version: 1,
+        sequence_number,
+        network_passphrase: Default::default(),
+        base_reserve: 1,
+    });
+
+    // 10 per ledger from ledger 10 to 20
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, 10, 20);
+    stream.by_seq = true;
+    stream.able_stop = true;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // the window is counted in ledgers as well, so a large timestamp doesn't expire it
+    set_ledger(1000, 12);
+    stream_client.with_source_account(&user_1)
+    .prep_stop(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    set_ledger(100000, 15);
+    stream_client.with_source_account(&user_1)
+    .exec_stop(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), 20);
+    assert!(stream_client.get_stream(&stream_id).1.cancelled);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(4))")]
+fn test_prepared_cancel_replayed(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let (sender_id, sender) = ed25519::generate(&env);
+
+    token_client.with_source_account(&user_1)
+    .mint(&Signature::Invoker, &BigInt::zero(&env), &sender_id, &BigInt::from_u64(&env,100));
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 100);
+    stream.from = sender_id.clone();
+    stream.able_stop = true;
+
+    let contract = Identifier::Contract(streaming_contract_id.clone());
+    let nonce = BigInt::zero(&env);
+    let signature = ed25519::sign(&env, &sender, &streaming_contract_id, symbol!("c_direct"), (&sender_id, &nonce, &crate::fingerprint(&env, &stream)));
+    let xfer_sig = ed25519::sign(&env, &sender, &token_contract_id, symbol!("xfer"), (&sender_id, &nonce, &contract, &stream.amount));
+    let stream_id = stream_client.c_direct(&signature, &nonce, &stream, &xfer_sig, &nonce);
+
+    set_timestamp(&env, start + 20);
+    let nonce = stream_client.nonce(&sender_id);
+    let signature = ed25519::sign(&env, &sender, &streaming_contract_id, symbol!("prep_stop"), (&sender_id, &nonce, &stream_id));
+    stream_client.prep_stop(&signature, &nonce, &stream_id);
+
+    // replaying the signature later can't move the snapshot forward
+    set_timestamp(&env, start + 50);
+    stream_client.prep_stop(&signature, &nonce, &stream_id);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(12))")]
+fn test_cancel_not_prepared(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 100);
+    stream.able_stop = true;
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_1)
+    .exec_stop(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+}
+
+#[test]
+fn test_split(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let user_3 = env.accounts().generate();
+
+    let start = env.ledger().timestamp();
+    let base = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+
+    let recipients = vec![&env, (Identifier::Account(user_2.clone()), 1u32), (Identifier::Account(user_3.clone()), 2u32)];
+    let stream_ids = stream_client.with_source_account(&user_1)
+    .c_split(&Signature::Invoker, &BigInt::zero(&env), &base, &recipients);
+
+    // 100 / 3 = 33, the remainder goes to the first recipient
+    let (stream_2, _) = stream_client.get_stream(&stream_ids.get_unchecked(0).unwrap());
+    let (stream_3, _) = stream_client.get_stream(&stream_ids.get_unchecked(1).unwrap());
+    assert_eq!(stream_2.to, Identifier::Account(user_2.clone()));
+    assert_eq!(stream_2.amount, BigInt::from_u32(&env, 34));
+    assert_eq!(stream_3.to, Identifier::Account(user_3.clone()));
+    assert_eq!(stream_3.amount, BigInt::from_u32(&env, 66));
+    assert_eq!(token_client.balance(&Identifier::Contract(streaming_contract_id)), BigInt::from_u32(&env, 100));
+
+    set_timestamp(&env, start + 5);
+    stream_client.with_source_account(&user_3)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_ids.get_unchecked(1).unwrap());
+    assert_eq!(token_client.balance(&Identifier::Account(user_3.clone())), BigInt::from_u32(&env, 33));
+    assert_eq!(token_client.balance(&Identifier::Account(user_2.clone())), BigInt::zero(&env));
+
+    set_timestamp(&env, start + 11);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_ids.get_unchecked(0).unwrap());
+    stream_client.with_source_account(&user_3)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_ids.get_unchecked(1).unwrap());
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 34));
+    assert_eq!(token_client.balance(&Identifier::Account(user_3)), BigInt::from_u32(&env, 66));
+}
+
+#[test]
+fn test_split_bond(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let user_3 = env.accounts().generate();
+
+    let start = env.ledger().timestamp();
+    let mut base = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    base.bond = BigInt::from_u32(&env, 31);
+    base.min_guar = BigInt::from_u32(&env, 10);
+
+    let recipients = vec![&env, (Identifier::Account(user_2.clone()), 1u32), (Identifier::Account(user_3), 2u32)];
+    let stream_ids = stream_client.with_source_account(&user_1)
+    .c_split(&Signature::Invoker, &BigInt::zero(&env), &base, &recipients);
+
+    // the bond is locked once in total, not once per stream
+    let (stream_2, _) = stream_client.get_stream(&stream_ids.get_unchecked(0).unwrap());
+    let (stream_3, _) = stream_client.get_stream(&stream_ids.get_unchecked(1).unwrap());
+    assert_eq!(stream_2.bond, 11);
+    assert_eq!(stream_3.bond, 20);
+    assert_eq!(stream_2.min_guar, 4);
+    assert_eq!(stream_3.min_guar, 6);
+    assert_eq!(token_client.balance(&Identifier::Contract(streaming_contract_id)), BigInt::from_u32(&env, 131));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(14))")]
+fn test_split_zero_weight(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+    let user_3 = env.accounts().generate();
+
+    let start = env.ledger().timestamp();
+    let base = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+
+    let recipients = vec![&env, (Identifier::Account(user_2.clone()), 1u32), (Identifier::Account(user_3), 0u32)];
+    stream_client.with_source_account(&user_1)
+    .c_split(&Signature::Invoker, &BigInt::zero(&env), &base, &recipients);
+}
+
+#[test]
+// the signature doesn't verify
+#[should_panic(expected = "Status(UnknownError(0))")]
+fn test_signed_split_changed(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, streaming_contract_id, stream_client) = setup(&env);
+    let (sender_id, sender) = ed25519::generate(&env);
+    let relayer = env.accounts().generate();
+
+    let start = env.ledger().timestamp();
+    let mut base = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    base.from = sender_id.clone();
+
+    let nonce = BigInt::zero(&env);
+    let recipients = vec![&env, (Identifier::Account(user_2), 1u32)];
+    let signature = ed25519::sign(&env, &sender, &streaming_contract_id, symbol!("c_split"), (&sender_id, &nonce, &crate::fingerprint(&env, &base), &recipients));
+
+    // a relayer can't send the streams to themselves
+    let recipients = vec![&env, (Identifier::Account(relayer), 1u32)];
+    stream_client.c_split(&signature, &nonce, &base, &recipients);
+}
+
+#[test]
+fn test_migrate(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let admin = env.accounts().generate();
+
+    let (new_token_id, new_token_client) = create_token_contract(&env, &user_1);
+    let swap_id = env.register_contract(None, MockSwap);
+    new_token_client.with_source_account(&user_1)
+    .mint(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Contract(swap_id.clone()), &BigInt::from_u64(&env,1000));
+
+    stream_client.init(&Identifier::Account(admin.clone()));
+    stream_client.with_source_account(&admin)
+    .allow_swap(&Signature::Invoker, &BigInt::zero(&env), &swap_id, &true);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 2);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    // 20 withdrawn, 20 vested but not withdrawn, 60 not vested
+    set_timestamp(&env, start + 4);
+    stream_client.with_source_account(&user_1)
+    .migrate(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &new_token_id, &swap_id);
+
+    // the vested 20 are paid out in the old token, only the rest is swapped
+    let contract = Identifier::Contract(streaming_contract_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2.clone())), BigInt::from_u32(&env, 40));
+    assert_eq!(token_client.balance(&contract), BigInt::zero(&env));
+    assert_eq!(new_token_client.balance(&contract), BigInt::from_u32(&env, 120));
+
+    let (stream, stream_data) = stream_client.get_stream(&stream_id);
+    assert_eq!(stream.token_c_id, new_token_id);
+    assert_eq!(stream.amount, BigInt::from_u32(&env, 120));
+    assert_eq!(stream_data.a_withdraw, BigInt::zero(&env));
+    assert_eq!(stream_data.a_vested, BigInt::zero(&env));
+
+    // the remaining 120 are spread over the 6 ticks that are left
+    set_timestamp(&env, start + 7);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(new_token_client.balance(&Identifier::Account(user_2.clone())), BigInt::from_u32(&env, 60));
+
+    set_timestamp(&env, start + 11);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(new_token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 120));
+    assert_eq!(stream_client.breakdown(&new_token_id), (BigInt::zero(&env), BigInt::zero(&env)));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(42))")]
+fn test_migrate_swap_not_allowed(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let (new_token_id, _) = create_token_contract(&env, &user_1);
+    let swap_id = env.register_contract(None, MockSwap);
+
+    let start = env.ledger().timestamp();
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10));
+
+    // the creator could pick a swap that gives the recipient almost nothing
+    stream_client.with_source_account(&user_1)
+    .migrate(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &new_token_id, &swap_id);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(7))")]
+fn test_migrate_ended(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+    let admin = env.accounts().generate();
+
+    let (new_token_id, _) = create_token_contract(&env, &user_1);
+    let swap_id = env.register_contract(None, MockSwap);
+    stream_client.init(&Identifier::Account(admin.clone()));
+
+    let start = env.ledger().timestamp();
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10));
+
+    set_timestamp(&env, start + 10);
+    stream_client.with_source_account(&admin)
+    .migrate(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &new_token_id, &swap_id);
+}
+
+#[test]
+fn test_relayed_withdraw(){
+    let env = Env::default();
+    let (user_1, _, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let relayer = env.accounts().generate();
+    let (recipient_id, recipient) = ed25519::generate(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_1, &token_contract_id, 100, start, start + 10);
+    stream.to = recipient_id.clone();
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 5);
+
+    let nonce = BigInt::zero(&env);
+    let signature = ed25519::sign(&env, &recipient, &streaming_contract_id, symbol!("w_stream"), (&recipient_id, &nonce, &id_fingerprint(&env, stream_id)));
+
+    stream_client.with_source_account(&relayer)
+    .w_stream(&signature, &nonce, &stream_id);
+
+    assert_eq!(token_client.balance(&recipient_id), BigInt::from_u32(&env, 50));
+    assert_eq!(token_client.balance(&Identifier::Account(relayer)), BigInt::zero(&env));
+    assert_eq!(stream_client.nonce(&recipient_id), BigInt::from_u32(&env, 1));
+}
+
+#[test]
+fn test_nonces(){
+    let env = Env::default();
+    let (user_1, _, token_contract_id, _, streaming_contract_id, stream_client) = setup(&env);
+    let (recipient_id, recipient) = ed25519::generate(&env);
+    let (fresh_id, _) = ed25519::generate(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_1, &token_contract_id, 100, start, start + 10);
+    stream.to = recipient_id.clone();
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 5);
+
+    let nonce = BigInt::zero(&env);
+    let signature = ed25519::sign(&env, &recipient, &streaming_contract_id, symbol!("w_stream"), (&recipient_id, &nonce, &id_fingerprint(&env, stream_id)));
+    stream_client.w_stream(&signature, &nonce, &stream_id);
+
+    assert_eq!(
+        stream_client.nonces(&vec![&env, recipient_id.clone(), fresh_id.clone()]),
+        vec![&env, BigInt::from_u32(&env, 1), BigInt::zero(&env)]
+    );
+    assert_eq!(
+        stream_client.nonces(&vec![&env, fresh_id, recipient_id]),
+        vec![&env, BigInt::zero(&env), BigInt::from_u32(&env, 1)]
+    );
+}
+
+#[test]
+fn test_withdraw_all(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 200, start, start + 20);
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    // not started yet
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start + 50, start + 60);
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    let cancelled_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 2);
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &cancelled_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2.clone())), BigInt::from_u32(&env, 20));
+
+    set_timestamp(&env, start + 5);
+    stream_client.with_source_account(&user_2)
+    .w_all(&Signature::Invoker, &BigInt::zero(&env));
+
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 20 + 50 + 50));
+}
+
+#[test]
+fn test_c_rate(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let rate_id = stream_client.with_source_account(&user_1)
+    .c_rate(&Signature::Invoker, &BigInt::zero(&env), &RateArgs{
+        from: Identifier::Account(user_1.clone()),
+        to: Identifier::Account(user_2.clone()),
+        token_c_id: token_contract_id.clone(),
+        per_tick: BigInt::from_u32(&env, 7),
+        tick_time: 3,
+        num_ticks: 10,
+        able_stop: false,
+    });
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 70, start, start + 30);
+    stream.tick_time = 3;
+    let total_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    let (rate_stream, _) = stream_client.get_stream(&rate_id);
+    assert_eq!(rate_stream.amount, 70);
+    assert_eq!(rate_stream.end_time, start + 30);
+    for time in [0, 2, 3, 10, 29, 30]{
+        set_timestamp(&env, start + time);
+        assert_eq!(stream_client.accrued(&rate_id), stream_client.accrued(&total_id));
+    }
+    set_timestamp(&env, start + 10);
+    assert_eq!(stream_client.accrued(&rate_id), 21);
+}
+
+#[test]
+fn test_c_rate_default_tick(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .set_dtick(&Signature::Invoker, &BigInt::zero(&env), &Some(5));
+
+    // the end is computed with the default tick, not with 0
+    let start = env.ledger().timestamp();
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_rate(&Signature::Invoker, &BigInt::zero(&env), &RateArgs{
+        from: Identifier::Account(user_1.clone()),
+        to: Identifier::Account(user_2),
+        token_c_id: token_contract_id,
+        per_tick: BigInt::from_u32(&env, 10),
+        tick_time: 0,
+        num_ticks: 4,
+        able_stop: false,
+    });
+
+    let (stream, _) = stream_client.get_stream(&stream_id);
+    assert_eq!(stream.tick_time, 5);
+    assert_eq!(stream.end_time, start + 20);
+    set_timestamp(&env, start + 1);
+    assert_eq!(stream_client.accrued(&stream_id), 0);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(8))")]
+fn test_c_rate_no_ticks(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    stream_client.with_source_account(&user_1)
+    .c_rate(&Signature::Invoker, &BigInt::zero(&env), &RateArgs{
+        from: Identifier::Account(user_1.clone()),
+        to: Identifier::Account(user_2),
+        token_c_id: token_contract_id,
+        per_tick: BigInt::from_u32(&env, 10),
+        tick_time: 5,
+        num_ticks: 0,
+        able_stop: false,
+    });
+}
+
+#[test]
+fn test_cancel_all(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    let cancellable_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    let fixed_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10));
+    // could only be cancelled until start + 2
+    stream.stop_until = start + 2;
+    let expired_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    stream.stop_until = u64::MAX;
+    let cancelled_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &cancelled_id);
+
+    set_timestamp(&env, start + 4);
+    let balance = token_client.balance(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .cancel_all(&Signature::Invoker, &BigInt::zero(&env));
+
+    assert!(stream_client.get_stream(&cancellable_id).1.cancelled);
+    assert!(!stream_client.get_stream(&fixed_id).1.cancelled);
+    assert!(!stream_client.get_stream(&expired_id).1.cancelled);
+    assert_eq!(token_client.balance(&Identifier::Account(user_1)), balance + 60);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), 40);
+    assert_invariants(&env, &stream_client, &streaming_contract_id, &token_contract_id, &[cancellable_id, fixed_id, expired_id, cancelled_id]);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(4))")]
+fn test_relayed_withdraw_replay(){
+    let env = Env::default();
+    let (user_1, _, token_contract_id, _, streaming_contract_id, stream_client) = setup(&env);
+    let relayer = env.accounts().generate();
+    let (recipient_id, recipient) = ed25519::generate(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_1, &token_contract_id, 100, start, start + 10);
+    stream.to = recipient_id.clone();
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 5);
+
+    let nonce = BigInt::zero(&env);
+    let signature = ed25519::sign(&env, &recipient, &streaming_contract_id, symbol!("w_stream"), (&recipient_id, &nonce, &id_fingerprint(&env, stream_id)));
+
+    stream_client.with_source_account(&relayer)
+    .w_stream(&signature, &nonce, &stream_id);
+
+    set_timestamp(&env, start + 7);
+    stream_client.with_source_account(&relayer)
+    .w_stream(&signature, &nonce, &stream_id);
+}
+
+#[test]
+fn test_claim_ext(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 4);
+    assert_eq!(stream_client.claimable(&stream_id), BigInt::from_u32(&env, 40));
+    // what has vested is not reduced by extending
+    assert_eq!(stream_client.claim_ext(&stream_id, &(start + 20)), BigInt::from_u32(&env, 40));
+
+    stream_client.with_source_account(&user_1)
+    .e_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &(start + 20));
+    assert_eq!(stream_client.claimable(&stream_id), BigInt::from_u32(&env, 40));
+
+    // the remaining 60 now vest over 16 ticks, 7 after 2 ticks
+    set_timestamp(&env, start + 6);
+    assert_eq!(stream_client.claimable(&stream_id), BigInt::from_u32(&env, 47));
+}
+
+#[test]
+fn test_start_in_past(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    set_timestamp(&env, 100);
+    for start in [50, 100, 150]{
+        let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 10, start, start + 10);
+        stream_client.with_source_account(&user_1)
+        .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    }
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .set_grace(&Signature::Invoker, &BigInt::zero(&env), &true, &10);
+
+    for start in [90, 100, 150]{
+        let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 10, start, start + 10);
+        stream_client.with_source_account(&user_1)
+        .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    }
+
+    stream_client.with_source_account(&user_1)
+    .set_grace(&Signature::Invoker, &BigInt::zero(&env), &false, &0);
+
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 10, 50, 60);
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(16))")]
+fn test_start_in_past_rejected(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .set_grace(&Signature::Invoker, &BigInt::zero(&env), &true, &10);
+
+    set_timestamp(&env, 100);
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 10, 89, 110);
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+}
+
+#[test]
+fn test_sweep(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let user_3 = env.accounts().generate();
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    token_client.with_source_account(&user_1)
+    .xfer(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Contract(streaming_contract_id.clone()), &BigInt::from_u32(&env, 5));
+
+    stream_client.with_source_account(&user_1)
+    .sweep(&Signature::Invoker, &BigInt::zero(&env), &token_contract_id, &Identifier::Account(user_3.clone()));
+
+    assert_eq!(token_client.balance(&Identifier::Account(user_3)), BigInt::from_u32(&env, 5));
+    assert_eq!(token_client.balance(&Identifier::Contract(streaming_contract_id)), BigInt::from_u32(&env, 100));
+
+    // the stream is still fully funded
+    set_timestamp(&env, start + 11);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 100));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(2))")]
+fn test_sweep_not_admin(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1));
+
+    stream_client.with_source_account(&user_2)
+    .sweep(&Signature::Invoker, &BigInt::zero(&env), &token_contract_id, &Identifier::Account(user_2.clone()));
+}
+
+#[test]
+fn test_create_direct(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let (sender_id, sender) = ed25519::generate(&env);
+
+    token_client.with_source_account(&user_1)
+    .mint(&Signature::Invoker, &BigInt::zero(&env), &sender_id, &BigInt::from_u64(&env,100));
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.from = sender_id.clone();
+
+    let contract = Identifier::Contract(streaming_contract_id.clone());
+    let nonce = BigInt::zero(&env);
+    let signature = ed25519::sign(&env, &sender, &streaming_contract_id, symbol!("c_direct"), (&sender_id, &nonce, &crate::fingerprint(&env, &stream)));
+    // no approval, the sender signs the transfer to the streaming contract directly
+    let xfer_sig = ed25519::sign(&env, &sender, &token_contract_id, symbol!("xfer"), (&sender_id, &nonce, &contract, &stream.amount));
+
+    let stream_id = stream_client.c_direct(&signature, &nonce, &stream, &xfer_sig, &nonce);
+
+    assert_eq!(token_client.balance(&sender_id), BigInt::zero(&env));
+    assert_eq!(token_client.balance(&contract), BigInt::from_u32(&env, 100));
+    assert_eq!(stream_client.get_stream(&stream_id).0.from, sender_id);
+}
+
+#[test]
+// the signature doesn't verify
+#[should_panic(expected = "Status(UnknownError(0))")]
+fn test_create_direct_changed(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let (sender_id, sender) = ed25519::generate(&env);
+    let relayer = env.accounts().generate();
+
+    token_client.with_source_account(&user_1)
+    .mint(&Signature::Invoker, &BigInt::zero(&env), &sender_id, &BigInt::from_u64(&env,100));
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.from = sender_id.clone();
+
+    let contract = Identifier::Contract(streaming_contract_id.clone());
+    let nonce = BigInt::zero(&env);
+    let signature = ed25519::sign(&env, &sender, &streaming_contract_id, symbol!("c_direct"), (&sender_id, &nonce, &crate::fingerprint(&env, &stream)));
+    let xfer_sig = ed25519::sign(&env, &sender, &token_contract_id, symbol!("xfer"), (&sender_id, &nonce, &contract, &stream.amount));
+
+    // whoever sees the signatures can't send the stream to themselves
+    stream.to = Identifier::Account(relayer);
+    stream_client.c_direct(&signature, &nonce, &stream, &xfer_sig, &nonce);
+}
+
+#[test]
+fn test_finalize(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+    let keeper = env.accounts().generate();
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.auto_push = true;
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 3);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    set_timestamp(&env, start + 11);
+    stream_client.with_source_account(&keeper)
+    .finalize(&stream_id);
+
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 100));
+    assert_eq!(stream_client.get_stream(&stream_id).1.a_withdraw, BigInt::from_u32(&env, 100));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(2))")]
+fn test_finalize_not_opted_in(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 11);
+    stream_client.finalize(&stream_id);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(3))")]
+fn test_invoker_nonzero_nonce(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::from_u32(&env, 1), &stream);
+}
+
+#[test]
+fn test_ed25519_nonce(){
+    let env = Env::default();
+    let (user_1, _, token_contract_id, _, streaming_contract_id, stream_client) = setup(&env);
+    let (recipient_id, recipient) = ed25519::generate(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_1, &token_contract_id, 100, start, start + 10);
+    stream.to = recipient_id.clone();
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    assert_eq!(stream_client.nonce(&recipient_id), BigInt::zero(&env));
+    for i in 0..3u32{
+        set_timestamp(&env, start + i as u64 + 1);
+        let nonce = BigInt::from_u32(&env, i);
+        let signature = ed25519::sign(&env, &recipient, &streaming_contract_id, symbol!("w_stream"), (&recipient_id, &nonce, &id_fingerprint(&env, stream_id)));
+        stream_client.w_stream(&signature, &nonce, &stream_id);
+    }
+    assert_eq!(stream_client.nonce(&recipient_id), BigInt::from_u32(&env, 3));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(4))")]
+fn test_ed25519_reused_nonce(){
+    let env = Env::default();
+    let (user_1, _, token_contract_id, _, streaming_contract_id, stream_client) = setup(&env);
+    let (recipient_id, recipient) = ed25519::generate(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_1, &token_contract_id, 100, start, start + 10);
+    stream.to = recipient_id.clone();
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 1);
+    let nonce = BigInt::zero(&env);
+    let signature = ed25519::sign(&env, &recipient, &streaming_contract_id, symbol!("w_stream"), (&recipient_id, &nonce, &id_fingerprint(&env, stream_id)));
+    stream_client.w_stream(&signature, &nonce, &stream_id);
+
+    // a new signature with the old nonce
+    set_timestamp(&env, start + 2);
+    let signature = ed25519::sign(&env, &recipient, &streaming_contract_id, symbol!("w_stream"), (&recipient_id, &nonce, &id_fingerprint(&env, stream_id)));
+    stream_client.w_stream(&signature, &nonce, &stream_id);
+}
+
+#[test]
+fn test_refunded(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+
+    let stream_id_1 = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    let stream_id_2 = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    assert_eq!(stream_client.refunded(&token_contract_id), BigInt::zero(&env));
+
+    set_timestamp(&env, start + 2);
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id_1);
+
+    set_timestamp(&env, start + 7);
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id_2);
+
+    assert_eq!(stream_client.refunded(&token_contract_id), BigInt::from_u32(&env, 80 + 30));
+}
+
+#[test]
+fn test_expected_nonce(){
+    let env = Env::default();
+    let (user_1, _, token_contract_id, _, streaming_contract_id, stream_client) = setup(&env);
+    let (recipient_id, recipient) = ed25519::generate(&env);
+
+    assert_eq!(stream_client.exp_nonce(&SigKind::Ed25519, &recipient_id), BigInt::zero(&env));
+    assert_eq!(stream_client.exp_nonce(&SigKind::Account, &Identifier::Account(user_1.clone())), BigInt::zero(&env));
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_1, &token_contract_id, 100, start, start + 10);
+    stream.to = recipient_id.clone();
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    for i in 0..2u64{
+        set_timestamp(&env, start + i + 1);
+        let nonce = stream_client.exp_nonce(&SigKind::Ed25519, &recipient_id);
+        let signature = ed25519::sign(&env, &recipient, &streaming_contract_id, symbol!("w_stream"), (&recipient_id, &nonce, &id_fingerprint(&env, stream_id)));
+        stream_client.w_stream(&signature, &nonce, &stream_id);
+    }
+
+    assert_eq!(stream_client.exp_nonce(&SigKind::Ed25519, &recipient_id), BigInt::from_u32(&env, 2));
+    // the stored nonce doesn't matter for invoker signatures
+    assert_eq!(stream_client.exp_nonce(&SigKind::Invoker, &recipient_id), BigInt::zero(&env));
+    assert_eq!(stream_client.nonce(&recipient_id), BigInt::from_u32(&env, 2));
+}
+
+#[test]
+fn test_hook(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let hook_id = env.register_contract(None, TestHook);
+    let hook_client = TestHookClient::new(&env, &hook_id);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .set_hook(&Signature::Invoker, &BigInt::zero(&env), &Some(hook_id));
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 3);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(hook_client.last(), (stream_id, BigInt::from_u32(&env, 30)));
+
+    set_timestamp(&env, start + 11);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(hook_client.last(), (stream_id, BigInt::from_u32(&env, 70)));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(17))")]
+fn test_withdraw_paused(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .set_wpause(&Signature::Invoker, &BigInt::zero(&env), &true);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 3);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+}
+
+#[test]
+fn test_cancel_while_withdraw_paused(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .set_wpause(&Signature::Invoker, &BigInt::zero(&env), &true);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 3);
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    assert_eq!(token_client.balance(&Identifier::Account(user_1.clone())), 970);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2.clone())), 30);
+
+    // withdrawals work again once unpaused
+    stream_client.with_source_account(&user_1)
+    .set_wpause(&Signature::Invoker, &BigInt::zero(&env), &false);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10));
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2.clone())), 60);
+}
+
+#[test]
+fn test_my_streams(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, contract_id, stream_client) = setup(&env);
+    let user_3 = env.accounts().generate();
+
+    token_client.with_source_account(&user_1)
+    .xfer(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Account(user_2.clone()), &BigInt::from_u32(&env, 100));
+    token_client.with_source_account(&user_2)
+    .approve(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Contract(contract_id), &BigInt::from_u32(&env, 100));
+
+    let start = env.ledger().timestamp();
+
+    // user_2 receives stream 0, isn't part of stream 1 and sends stream 2
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 10, start, start + 10));
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_3, &token_contract_id, 10, start, start + 10));
+    stream_client.with_source_account(&user_2)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_2, &user_3, &token_contract_id, 10, start, start + 10));
+    // a stream to self is in both indices of user_1
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_1, &token_contract_id, 10, start, start + 10));
+
+    assert_eq!(stream_client.my_streams(&Identifier::Account(user_2)), vec![&env, 0, 2]);
+    assert_eq!(stream_client.my_streams(&Identifier::Account(user_1)), vec![&env, 0, 1, 3]);
+    assert_eq!(stream_client.my_streams(&Identifier::Account(user_3)), vec![&env, 1, 2]);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(4))")]
+fn test_extend_replayed(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let (sender_id, sender) = ed25519::generate(&env);
+
+    token_client.with_source_account(&user_1)
+    .mint(&Signature::Invoker, &BigInt::zero(&env), &sender_id, &BigInt::from_u64(&env,100));
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.from = sender_id.clone();
+    stream.able_stop = true;
+
+    let contract = Identifier::Contract(streaming_contract_id.clone());
+    let nonce = BigInt::zero(&env);
+    let signature = ed25519::sign(&env, &sender, &streaming_contract_id, symbol!("c_direct"), (&sender_id, &nonce, &crate::fingerprint(&env, &stream)));
+    let xfer_sig = ed25519::sign(&env, &sender, &token_contract_id, symbol!("xfer"), (&sender_id, &nonce, &contract, &stream.amount));
+    let stream_id = stream_client.c_direct(&signature, &nonce, &stream, &xfer_sig, &nonce);
+
+    let nonce = stream_client.nonce(&sender_id);
+    let extend_sig = ed25519::sign(&env, &sender, &streaming_contract_id, symbol!("e_stream"), (&sender_id, &nonce, &stream_id, &(start + 20)));
+    stream_client.e_stream(&extend_sig, &nonce, &stream_id, &(start + 20));
+
+    let r_nonce = stream_client.nonce(&sender_id);
+    let reduce_by = BigInt::from_u32(&env, 50);
+    let signature = ed25519::sign(&env, &sender, &streaming_contract_id, symbol!("r_stream"), (&sender_id, &r_nonce, &stream_id, &reduce_by));
+    stream_client.r_stream(&signature, &r_nonce, &stream_id, &reduce_by);
+    assert_eq!(stream_client.get_stream(&stream_id).0.end_time, start + 10);
+
+    // the old signature can't stretch the reduced stream again
+    stream_client.e_stream(&extend_sig, &nonce, &stream_id, &(start + 20));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(4))")]
+fn test_cancel_replayed(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let (sender_id, sender) = ed25519::generate(&env);
+
+    token_client.with_source_account(&user_1)
+    .mint(&Signature::Invoker, &BigInt::zero(&env), &sender_id, &BigInt::from_u64(&env,100));
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.from = sender_id.clone();
+    stream.able_stop = true;
+
+    let contract = Identifier::Contract(streaming_contract_id.clone());
+    let nonce = BigInt::zero(&env);
+    let signature = ed25519::sign(&env, &sender, &streaming_contract_id, symbol!("c_direct"), (&sender_id, &nonce, &crate::fingerprint(&env, &stream)));
+    let xfer_sig = ed25519::sign(&env, &sender, &token_contract_id, symbol!("xfer"), (&sender_id, &nonce, &contract, &stream.amount));
+    let stream_id = stream_client.c_direct(&signature, &nonce, &stream, &xfer_sig, &nonce);
+
+    set_timestamp(&env, start + 3);
+    let nonce = stream_client.nonce(&sender_id);
+    let signature = ed25519::sign(&env, &sender, &streaming_contract_id, symbol!("s_stream"), (&sender_id, &nonce, &id_fingerprint(&env, stream_id)));
+    stream_client.s_stream(&signature, &nonce, &stream_id);
+    assert_eq!(token_client.balance(&sender_id), 70);
+
+    // submitting the same signature again fails on the nonce instead of on the cancelled stream
+    stream_client.s_stream(&signature, &nonce, &stream_id);
+}
+
+#[test]
+fn test_amount_display(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // the test token has 7 decimals
+    assert_eq!(stream_client.amt_disp(&stream_id), (BigInt::from_u32(&env, 100), 7));
+}
+
+#[test]
+fn test_decimals_snapshot(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    assert_eq!(stream_client.get_stream(&stream_id).1.decimals, 7);
+}
+
+#[test]
+fn test_reduce(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // 40 vested, take back half of the remaining 60
+    set_timestamp(&env, start + 4);
+    stream_client.with_source_account(&user_1)
+    .r_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &BigInt::from_u32(&env, 30));
+
+    assert_eq!(token_client.balance(&Identifier::Account(user_1.clone())), 930);
+    assert_eq!(stream_client.claimable(&stream_id), 40);
+
+    let (stream, _) = stream_client.get_stream(&stream_id);
+    assert_eq!(stream.amount, 70);
+    assert_eq!(stream.end_time, start + 7);
+
+    // same rate as before, but it ends earlier
+    set_timestamp(&env, start + 5);
+    assert_eq!(stream_client.claimable(&stream_id), 50);
+
+    set_timestamp(&env, start + 10);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2.clone())), 70);
+}
+
+#[test]
+fn test_reduce_completes_bond(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    stream.bond = BigInt::from_u32(&env, 20);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 4);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    // everything that is left is taken back, so the stream is completed and the bond goes back to the creator
+    stream_client.with_source_account(&user_1)
+    .r_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &BigInt::from_u32(&env, 60));
+
+    assert!(stream_client.get_stream(&stream_id).1.completed);
+    assert_eq!(token_client.balance(&Identifier::Account(user_1)), 960);
+    assert_eq!(stream_client.tvl(&token_contract_id), 0);
+    assert_invariants(&env, &stream_client, &streaming_contract_id, &token_contract_id, &[stream_id]);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(18))")]
+fn test_reduce_too_much(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // only 60 is unvested
+    set_timestamp(&env, start + 4);
+    stream_client.with_source_account(&user_1)
+    .r_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &BigInt::from_u32(&env, 61));
+}
+
+#[test]
+fn test_accrued(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.tick_time = 3;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    for time in [0, 2, 4, 7, 9, 10, 11]{
+        set_timestamp(&env, start + time);
+        if time == 4 || time == 10{
+            stream_client.with_source_account(&user_2)
+            .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+        }
+        let a_withdraw = stream_client.get_stream(&stream_id).1.a_withdraw;
+        assert_eq!(stream_client.accrued(&stream_id), stream_client.claimable(&stream_id) + a_withdraw);
+    }
+    assert_eq!(stream_client.accrued(&stream_id), 100);
+}
+
+#[test]
+fn test_rounding(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    // 3 ticks for 10, nothing is left over for after the end
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 10, start, start + 3);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    assert_eq!(stream_client.dust(&stream_id), 0);
+
+    set_timestamp(&env, start + 1);
+    assert_eq!(stream_client.claimable(&stream_id), 3);
+    set_timestamp(&env, start + 2);
+    assert_eq!(stream_client.claimable(&stream_id), 6);
+    set_timestamp(&env, start + 3);
+    assert_eq!(stream_client.claimable(&stream_id), 10);
+}
+
+#[test]
+fn test_shorter_than_tick(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    // a single tick that is cut short by the end, nothing vests until the stream is over
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 5);
+    stream.tick_time = 10;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 5);
+    assert_eq!(stream_client.claimable(&stream_id), 0);
+    set_timestamp(&env, start + 6);
+    assert_eq!(stream_client.claimable(&stream_id), 100);
+}
+
+#[test]
+fn test_large_amount(){
+    let env = Env::default();
+    let user_1 = env.accounts().generate();
+    let user_2 = env.accounts().generate();
+    let (token_contract_id, token_client) = create_token_contract(&env, &user_1);
+    let (streaming_contract_id, stream_client) = create_streaming_contract(&env);
+
+    // 10^30 + 7 over 999_983 ticks, the amount per tick isn't a whole number
+    let amount = BigInt::from_u64(&env, 1_000_000_000_000_000) * BigInt::from_u64(&env, 1_000_000_000_000_000) + 7u32;
+    token_client.with_source_account(&user_1)
+    .mint(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Account(user_1.clone()), &amount);
+    token_client.with_source_account(&user_1)
+    .approve(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Contract(streaming_contract_id), &amount);
+
+    let start = env.ledger().timestamp();
+    let ticks = 999_983u64;
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 0, start, start + ticks);
+    stream.amount = amount.clone();
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    let recipient = Identifier::Account(user_2.clone());
+    for elapsed in [1, 12_345, 500_000, ticks - 1]{
+        set_timestamp(&env, start + elapsed);
+        stream_client.with_source_account(&user_2)
+        .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+        assert_eq!(token_client.balance(&recipient), &amount * elapsed / ticks);
+    }
+
+    set_timestamp(&env, start + ticks);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&recipient), amount);
+    assert_eq!(stream_client.claimable(&stream_id), 0);
+}
+
+#[test]
+fn test_default_tick(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .set_dtick(&Signature::Invoker, &BigInt::zero(&env), &Some(5));
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.tick_time = 0;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    assert_eq!(stream_client.get_stream(&stream_id).0.tick_time, 5);
+    set_timestamp(&env, start + 7);
+    assert_eq!(stream_client.claimable(&stream_id), 50);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(19))")]
+fn test_no_default_tick(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.tick_time = 0;
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+}
+
+#[test]
+fn test_max_streams(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .set_max(&Signature::Invoker, &BigInt::zero(&env), &Some(2));
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    let other_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // cancelling a stream frees a slot
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // and so does withdrawing everything
+    set_timestamp(&env, start + 10);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &other_id);
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start + 10, start + 20));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(20))")]
+fn test_too_many_streams(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .set_max(&Signature::Invoker, &BigInt::zero(&env), &Some(2));
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+
+    for _ in 0..3{
+        stream_client.with_source_account(&user_1)
+        .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    }
+}
+
+#[test]
+fn test_avg_end(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+    let (other_token_id, _) = create_token_contract(&env, &user_1);
+
+    assert_eq!(stream_client.avg_end(&Identifier::Account(user_1.clone()), &token_contract_id), 0);
+
+    let start = env.ledger().timestamp();
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 300, start, start + 10));
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 50));
+
+    // (300 * 10 + 100 * 50) / 400
+    assert_eq!(stream_client.avg_end(&Identifier::Account(user_1.clone()), &token_contract_id), start + 20);
+    assert_eq!(stream_client.avg_end(&Identifier::Account(user_1), &other_token_id), 0);
+}
+
+#[test]
+fn test_withdraw_schedule(){
+    let env = Env::default();
+    let (user_1, _, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let (recipient_id, recipient) = ed25519::generate(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_1, &token_contract_id, 100, start, start + 10);
+    stream.to = recipient_id.clone();
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    let times = vec![&env, start + 3, start + 6];
+    let nonce = BigInt::zero(&env);
+    let signature = ed25519::sign(&env, &recipient, &streaming_contract_id, symbol!("reg_sched"), (&recipient_id, &nonce, &stream_id, &times));
+    stream_client.reg_sched(&signature, &nonce, &stream_id, &times);
+
+    // a keeper executes the entries, without signatures of the recipient
+    set_timestamp(&env, start + 3);
+    stream_client.exec_entry(&stream_id, &0);
+    assert_eq!(token_client.balance(&recipient_id), 30);
+
+    set_timestamp(&env, start + 7);
+    stream_client.exec_entry(&stream_id, &1);
+    assert_eq!(token_client.balance(&recipient_id), 70);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(23))")]
+fn test_withdraw_schedule_not_due(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_2)
+    .reg_sched(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &vec![&env, start + 3, start + 6]);
+
+    set_timestamp(&env, start + 3);
+    stream_client.exec_entry(&stream_id, &1);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(22))")]
+fn test_withdraw_schedule_executed_twice(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_2)
+    .reg_sched(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &vec![&env, start + 3]);
+
+    set_timestamp(&env, start + 3);
+    stream_client.exec_entry(&stream_id, &0);
+    set_timestamp(&env, start + 5);
+    stream_client.exec_entry(&stream_id, &0);
+}
+
+#[test]
+fn test_preview_cancel(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+    let user_1_id = Identifier::Account(user_1.clone());
+    let user_2_id = Identifier::Account(user_2.clone());
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+
+    for time in [0, 3, 7, 11]{
+        set_timestamp(&env, start);
+        let stream_id = stream_client.with_source_account(&user_1)
+        .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+        set_timestamp(&env, start + time);
+        let (refund, payout) = stream_client.prev_stop(&stream_id);
+
+        let balance_1 = token_client.balance(&user_1_id);
+        let balance_2 = token_client.balance(&user_2_id);
+        stream_client.with_source_account(&user_1)
+        .s_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+        assert_eq!(token_client.balance(&user_1_id) - balance_1, refund);
+        assert_eq!(token_client.balance(&user_2_id) - balance_2, payout);
+
+        // nothing left to cancel
+        assert_eq!(stream_client.prev_stop(&stream_id), (BigInt::zero(&env), BigInt::zero(&env)));
+    }
+
+    // not cancellable
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10));
+    assert_eq!(stream_client.prev_stop(&stream_id), (BigInt::zero(&env), BigInt::zero(&env)));
+}
+
+#[test]
+fn test_check_hash(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    let agreed = env.compute_hash_sha256(&stream.clone().serialize(&env));
+    assert!(stream_client.chk_hash(&stream_id, &agreed));
+
+    let mut other = stream;
+    other.amount = BigInt::from_u32(&env, 99);
+    let other = env.compute_hash_sha256(&other.serialize(&env));
+    assert!(!stream_client.chk_hash(&stream_id, &other));
+}
+
+#[test]
+fn test_delegate(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+    let delegate = env.accounts().generate();
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_2)
+    .set_deleg(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &Identifier::Account(delegate.clone()));
+
+    // the funds go to the recipient, not the delegate
+    set_timestamp(&env, start + 3);
+    stream_client.with_source_account(&delegate)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), 30);
+    assert_eq!(token_client.balance(&Identifier::Account(delegate)), 0);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(2))")]
+fn test_delegate_third_party(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+    let delegate = env.accounts().generate();
+    let other = env.accounts().generate();
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_2)
+    .set_deleg(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &Identifier::Account(delegate));
+
+    set_timestamp(&env, start + 3);
+    stream_client.with_source_account(&other)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(2))")]
+fn test_delegate_cleared(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+    let delegate = env.accounts().generate();
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_2)
+    .set_deleg(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &Identifier::Account(delegate.clone()));
+    stream_client.with_source_account(&user_2)
+    .clr_deleg(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    set_timestamp(&env, start + 3);
+    stream_client.with_source_account(&delegate)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+}
+
+#[test]
+fn test_vested_at(){
+    let env = Env::default();
+    let user_1 = env.accounts().generate();
+    let user_2 = env.accounts().generate();
+    let (token_contract_id, _) = create_token_contract(&env, &user_1);
+
+    // 4 ticks of 3 seconds, 25 per tick
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, 10, 22);
+    stream.tick_time = 3;
+    let stream_data = StreamData{
+        a_withdraw: BigInt::zero(&env),
+        cancelled: false,
+        a_vested: BigInt::zero(&env),
+        decimals: 7,
+        last_wd: 0,
+        completed: false,
+    };
+
+    assert_eq!(vested_at(&stream, &stream_data, 0), 0);
+    assert_eq!(vested_at(&stream, &stream_data, 10), 0);
+    assert_eq!(vested_at(&stream, &stream_data, 12), 0);
+    assert_eq!(vested_at(&stream, &stream_data, 13), 25);
+    assert_eq!(vested_at(&stream, &stream_data, 16), 50);
+    assert_eq!(vested_at(&stream, &stream_data, 19), 75);
+    assert_eq!(vested_at(&stream, &stream_data, 22), 100);
+    assert_eq!(vested_at(&stream, &stream_data, 23), 100);
+    assert_eq!(vested_at(&stream, &stream_data, u64::MAX), 100);
+}
+
+#[test]
+fn test_ladder(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10));
+    // 2.5 per tick
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 50, start, start + 20));
+
+    let ladder = stream_client.ladder(&token_contract_id, &10, &3, &0, &10);
+    assert_eq!(ladder, vec![&env, BigInt::from_u32(&env, 125), BigInt::from_u32(&env, 25), BigInt::zero(&env)]);
+    // only the second stream
+    let ladder = stream_client.ladder(&token_contract_id, &10, &3, &1, &1);
+    assert_eq!(ladder, vec![&env, BigInt::from_u32(&env, 25), BigInt::from_u32(&env, 25), BigInt::zero(&env)]);
+
+    // what has vested already isn't counted
+    set_timestamp(&env, start + 5);
+    let ladder = stream_client.ladder(&token_contract_id, &10, &2, &0, &10);
+    assert_eq!(ladder, vec![&env, BigInt::from_u32(&env, 75), BigInt::from_u32(&env, 13)]);
+}
+
+#[test]
+fn test_sent_page(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+    let from = Identifier::Account(user_1.clone());
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 10, start, start + 10);
+    for _ in 0..5{
+        stream_client.with_source_account(&user_1)
+        .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    }
+
+    assert_eq!(stream_client.sent_page(&from, &0, &2), vec![&env, 0, 1]);
+    assert_eq!(stream_client.sent_page(&from, &2, &2), vec![&env, 2, 3]);
+    assert_eq!(stream_client.sent_page(&from, &4, &2), vec![&env, 4]);
+    assert_eq!(stream_client.sent_page(&from, &5, &2).len(), 0);
+    // the limit is clamped
+    assert_eq!(stream_client.sent_page(&from, &1, &u32::MAX).len(), 4);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(24))")]
+fn test_reduce_below_guarantee(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    stream.min_guar = BigInt::from_u32(&env, 80);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 4);
+    stream_client.with_source_account(&user_1)
+    .r_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &BigInt::from_u32(&env, 30));
+}
+
+#[test]
+fn test_cancel_guarantee(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    stream.min_guar = BigInt::from_u32(&env, 50);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // a reduction that stays above the guarantee is fine
+    set_timestamp(&env, start + 2);
+    stream_client.with_source_account(&user_1)
+    .r_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &BigInt::from_u32(&env, 40));
+
+    // only 30 vested, but the recipient is guaranteed 50
+    set_timestamp(&env, start + 3);
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), 50);
+    assert_eq!(token_client.balance(&Identifier::Account(user_1)), 950);
+}
+
+#[test]
+fn test_income(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+    let (other_token_id, _) = create_token_contract(&env, &user_1);
+    let to = Identifier::Account(user_2.clone());
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    let stream_id_1 = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    let stream_id_2 = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // outside of the window
+    set_timestamp(&env, start + 2);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id_1);
+
+    // inside of the window, the payout of a cancellation counts as well
+    set_timestamp(&env, start + 5);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id_1);
+    set_timestamp(&env, start + 6);
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id_2);
+
+    assert_eq!(stream_client.income(&to, &token_contract_id, &(start + 3), &(start + 10), &0, &10), 90);
+    assert_eq!(stream_client.income(&to, &token_contract_id, &start, &(start + 10), &0, &10), 110);
+    assert_eq!(stream_client.income(&to, &other_token_id, &start, &(start + 10), &0, &10), 0);
+    // one stream at a time
+    assert_eq!(stream_client.income(&to, &token_contract_id, &start, &(start + 10), &0, &1), 50);
+    assert_eq!(stream_client.income(&to, &token_contract_id, &start, &(start + 10), &1, &1), 60);
+    assert_eq!(stream_client.income(&to, &token_contract_id, &start, &(start + 10), &2, &1), 0);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(25))")]
+fn test_wind_down_no_creation(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .wind_down(&Signature::Invoker, &BigInt::zero(&env));
+
+    let start = env.ledger().timestamp();
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10));
+}
+
+#[test]
+fn test_wind_down_settle(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+
+    // irrevocable, ends long after the wind down is done
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 20 * 24 * 60 * 60);
+    stream.tick_time = 24 * 60 * 60;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_1)
+    .wind_down(&Signature::Invoker, &BigInt::zero(&env));
+
+    // 7 of 20 days vested
+    set_timestamp(&env, start + 7 * 24 * 60 * 60);
+    stream_client.wd_settle(&stream_id);
+
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), 35);
+    assert_eq!(token_client.balance(&Identifier::Account(user_1)), 965);
+    assert!(stream_client.get_stream(&stream_id).1.cancelled);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(26))")]
+fn test_wind_down_too_early(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+
+    let start = env.ledger().timestamp();
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10));
+
+    stream_client.with_source_account(&user_1)
+    .wind_down(&Signature::Invoker, &BigInt::zero(&env));
+
+    set_timestamp(&env, start + 5);
+    stream_client.wd_settle(&stream_id);
+}
+
+#[test]
+fn test_tvl(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    let stream_id_1 = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    let stream_id_2 = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    assert_eq!(stream_client.tvl(&token_contract_id), 200);
+
+    set_timestamp(&env, start + 3);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id_1);
+    assert_eq!(stream_client.tvl(&token_contract_id), 170);
+
+    // the payout and the refund both leave the contract
+    set_timestamp(&env, start + 4);
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id_2);
+    assert_eq!(stream_client.tvl(&token_contract_id), 70);
+
+    set_timestamp(&env, start + 11);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id_1);
+    assert_eq!(stream_client.tvl(&token_contract_id), 0);
+}
+
+#[test]
+fn test_nb_recipients(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+    let user_3 = env.accounts().generate();
+    let from = Identifier::Account(user_1.clone());
+
+    assert_eq!(stream_client.nb_recips(&from), 0);
+
+    let start = env.ledger().timestamp();
+    for to in [&user_2, &user_2, &user_3]{
+        stream_client.with_source_account(&user_1)
+        .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, to, &token_contract_id, 10, start, start + 10));
+    }
+
+    assert_eq!(stream_client.nb_recips(&from), 2);
+}
+
+#[test]
+fn test_operator(){
+    let env = Env::default();
+    let (funder, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+    let operator = env.accounts().generate();
+
+    // the funder approved the contract in `setup`, and lets the operator spend that approval
+    stream_client.with_source_account(&funder)
+    .set_oper(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Account(operator.clone()), &true);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &funder, &user_2, &token_contract_id, 100, start, start + 10);
+    stream_client.with_source_account(&operator)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    assert_eq!(token_client.balance(&Identifier::Account(funder)), 900);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(2))")]
+fn test_not_operator(){
+    let env = Env::default();
+    let (funder, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+    let operator = env.accounts().generate();
+
+    stream_client.with_source_account(&funder)
+    .set_oper(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Account(operator.clone()), &true);
+    stream_client.with_source_account(&funder)
+    .set_oper(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Account(operator.clone()), &false);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &funder, &user_2, &token_contract_id, 100, start, start + 10);
+    stream_client.with_source_account(&operator)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+}
+
+#[test]
+fn test_reconcile(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, contract_id, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+
+    let start = env.ledger().timestamp();
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10));
+
+    // fully funded, nothing to do
+    assert!(!stream_client.with_source_account(&user_1)
+    .reconcile(&Signature::Invoker, &BigInt::zero(&env), &stream_id));
+
+    // the token admin takes 60 away from the contract
+    token_client.with_source_account(&user_1)
+    .burn(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Contract(contract_id.clone()), &BigInt::from_u32(&env, 60));
+
+    // 30 has vested and goes to the recipient, the remaining 10 goes back to the creator
+    set_timestamp(&env, start + 3);
+    assert!(stream_client.with_source_account(&user_1)
+    .reconcile(&Signature::Invoker, &BigInt::zero(&env), &stream_id));
+
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), 30);
+    assert_eq!(token_client.balance(&Identifier::Account(user_1)), 910);
+    assert_eq!(token_client.balance(&Identifier::Contract(contract_id)), 0);
+    assert_eq!(stream_client.tvl(&token_contract_id), 0);
+    assert!(stream_client.get_stream(&stream_id).1.cancelled);
+}
+
+#[test]
+fn test_reconcile_other_streams(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, contract_id, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+
+    let start = env.ledger().timestamp();
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10));
+    let other_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10));
+
+    token_client.with_source_account(&user_1)
+    .burn(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Contract(contract_id.clone()), &BigInt::from_u32(&env, 60));
+
+    // the 100 of the other stream doesn't back this one, only 40 is left for it
+    set_timestamp(&env, start + 3);
+    assert!(stream_client.with_source_account(&user_1)
+    .reconcile(&Signature::Invoker, &BigInt::zero(&env), &stream_id));
+    assert_eq!(token_client.balance(&Identifier::Account(user_2.clone())), 30);
+    assert_eq!(token_client.balance(&Identifier::Account(user_1.clone())), 810);
+    assert_invariants(&env, &stream_client, &contract_id, &token_contract_id, &[stream_id, other_id]);
+
+    // the other stream is still fully backed
+    set_timestamp(&env, start + 10);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &other_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), 130);
+}
+
+#[test]
+fn test_no_residual(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, contract_id, stream_client) = setup(&env);
+
+    // 33 per tick, 1 is left over by the rounding
+    let start = env.ledger().timestamp();
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 3));
+
+    set_timestamp(&env, start + 2);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2.clone())), 66);
+
+    // the final withdrawal includes the rounding, so nothing of the stream stays in the contract
+    set_timestamp(&env, start + 4);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), 100);
+    assert_eq!(token_client.balance(&Identifier::Contract(contract_id)), 0);
+    assert_eq!(stream_client.breakdown(&token_contract_id), (BigInt::zero(&env), BigInt::zero(&env)));
+}
+
+#[test]
+// the signature doesn't verify
+#[should_panic(expected = "Status(UnknownError(0))")]
+fn test_signature_other_stream(){
+    let env = Env::default();
+    let (user_1, _, token_contract_id, _, streaming_contract_id, stream_client) = setup(&env);
+    let (recipient_id, recipient) = ed25519::generate(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_1, &token_contract_id, 100, start, start + 10);
+    stream.to = recipient_id.clone();
+
+    let stream_id_a = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    let stream_id_b = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // signed for stream a, submitted for stream b
+    set_timestamp(&env, start + 1);
+    let nonce = BigInt::zero(&env);
+    let signature = ed25519::sign(&env, &recipient, &streaming_contract_id, symbol!("w_stream"), (&recipient_id, &nonce, &id_fingerprint(&env, stream_id_a)));
+    stream_client.w_stream(&signature, &nonce, &stream_id_b);
+}
+
+#[test]
+fn test_signed_create(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let (sender_id, sender) = ed25519::generate(&env);
+
+    token_client.with_source_account(&user_1)
+    .xfer(&Signature::Invoker, &BigInt::zero(&env), &sender_id, &BigInt::from_u32(&env, 100));
+    let nonce = BigInt::zero(&env);
+    let contract = Identifier::Contract(streaming_contract_id.clone());
+    let approve_sig = ed25519::sign(&env, &sender, &token_contract_id, symbol!("approve"), (&sender_id, &nonce, &contract, &BigInt::from_u32(&env, 100)));
+    token_client.approve(&approve_sig, &nonce, &contract, &BigInt::from_u32(&env, 100));
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.from = sender_id.clone();
+
+    let signature = ed25519::sign(&env, &sender, &streaming_contract_id, symbol!("c_stream"), (&sender_id, &nonce, &crate::fingerprint(&env, &stream)));
+    let stream_id = stream_client.c_stream(&signature, &nonce, &stream);
+    assert_eq!(stream_client.get_stream(&stream_id).0.from, sender_id);
+}
+
+#[test]
+// the signature doesn't verify
+#[should_panic(expected = "Status(UnknownError(0))")]
+fn test_signed_create_changed(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, streaming_contract_id, stream_client) = setup(&env);
+    let (sender_id, sender) = ed25519::generate(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.from = sender_id.clone();
+
+    let nonce = BigInt::zero(&env);
+    let signature = ed25519::sign(&env, &sender, &streaming_contract_id, symbol!("c_stream"), (&sender_id, &nonce, &crate::fingerprint(&env, &stream)));
+
+    // a relayer can't redirect the stream
+    stream.to = Identifier::Account(user_1);
+    stream_client.c_stream(&signature, &nonce, &stream);
+}
+
+#[test]
+fn test_min_duration_and_tick(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+    let start = env.ledger().timestamp();
+
+    // no minimum by default
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 10, start, start + 1));
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .set_mins(&Signature::Invoker, &BigInt::zero(&env), &Some(10), &Some(2));
+
+    // at and above the minimums
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 10, start, start + 10);
+    stream.tick_time = 2;
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    stream.end_time = start + 20;
+    stream.tick_time = 5;
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // clearing the minimums allows short streams again
+    stream_client.with_source_account(&user_1)
+    .set_mins(&Signature::Invoker, &BigInt::zero(&env), &None, &None);
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 10, start, start + 1));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(27))")]
+fn test_below_min_duration(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .set_mins(&Signature::Invoker, &BigInt::zero(&env), &Some(10), &None);
+
+    let start = env.ledger().timestamp();
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 10, start, start + 9));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(28))")]
+fn test_below_min_tick(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .set_mins(&Signature::Invoker, &BigInt::zero(&env), &None, &Some(2));
+
+    let start = env.ledger().timestamp();
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &new_stream(&env, &user_1, &user_2, &token_contract_id, 10, start, start + 10));
+}
+
+#[test]
+fn test_timing(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    // ticks at 14, 18 and 20
+    let start = env.ledger().timestamp() + 10;
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.tick_time = 4;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start - 3);
+    assert_eq!(stream_client.timing(&stream_id), (7, 13, 0));
+    set_timestamp(&env, start);
+    assert_eq!(stream_client.timing(&stream_id), (4, 10, 0));
+    set_timestamp(&env, start + 5);
+    assert_eq!(stream_client.timing(&stream_id), (3, 5, 1));
+    // the last tick is cut short by the end
+    set_timestamp(&env, start + 9);
+    assert_eq!(stream_client.timing(&stream_id), (1, 1, 2));
+    set_timestamp(&env, start + 10);
+    assert_eq!(stream_client.timing(&stream_id), (0, 0, 3));
+    set_timestamp(&env, start + 100);
+    assert_eq!(stream_client.timing(&stream_id), (0, 0, 3));
+}
+
+#[test]
+fn test_not_prefunded(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.prefunded = false;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // nothing is locked
+    let contract = Identifier::Contract(streaming_contract_id.clone());
+    assert_eq!(token_client.balance(&contract), BigInt::zero(&env));
+    assert_eq!(token_client.balance(&Identifier::Account(user_1.clone())), BigInt::from_u32(&env, 1000));
+    assert_eq!(stream_client.tvl(&token_contract_id), BigInt::zero(&env));
+
+    set_timestamp(&env, start + 3);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2.clone())), BigInt::from_u32(&env, 30));
+
+    set_timestamp(&env, start + 7);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2.clone())), BigInt::from_u32(&env, 70));
+
+    set_timestamp(&env, start + 11);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 100));
+    assert_eq!(token_client.balance(&Identifier::Account(user_1)), BigInt::from_u32(&env, 900));
+    assert_eq!(token_client.balance(&contract), BigInt::zero(&env));
+    assert_eq!(stream_client.get_stream(&stream_id).1.a_withdraw, BigInt::from_u32(&env, 100));
+}
+
+#[test]
+fn test_not_prefunded_cancel(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.prefunded = false;
+    stream.able_stop = true;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 4);
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    // the creator only paid what had vested
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 40));
+    assert_eq!(token_client.balance(&Identifier::Account(user_1)), BigInt::from_u32(&env, 960));
+    assert_eq!(stream_client.refunded(&token_contract_id), BigInt::zero(&env));
+}
+
+#[test]
+#[should_panic]
+fn test_not_prefunded_allowance_too_low(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.prefunded = false;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    token_client.with_source_account(&user_1)
+    .approve(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Contract(streaming_contract_id), &BigInt::from_u32(&env, 10));
+
+    // 50 vested, only 10 can be pulled
+    set_timestamp(&env, start + 5);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+}
+
+#[test]
+fn test_withdraw_interval(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+
+    let start = 100;
+    set_timestamp(&env, start);
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.min_wd_int = 5;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // the first withdrawal isn't limited
+    set_timestamp(&env, start + 1);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(stream_client.get_stream(&stream_id).1.last_wd, start + 1);
+
+    set_timestamp(&env, start + 6);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 60));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(30))")]
+fn test_withdraw_too_soon(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = 100;
+    set_timestamp(&env, start);
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.min_wd_int = 5;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 1);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    set_timestamp(&env, start + 5);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+}
+
+#[test]
+fn test_renounce(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+
+    // not cancellable by the creator
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 2);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    set_timestamp(&env, start + 6);
+    stream_client.with_source_account(&user_2)
+    .renounce(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 60));
+    assert_eq!(token_client.balance(&Identifier::Account(user_1)), BigInt::from_u32(&env, 940));
+    assert!(stream_client.get_stream(&stream_id).1.cancelled);
+    assert_eq!(stream_client.tvl(&token_contract_id), BigInt::zero(&env));
+}
+
+#[test]
+fn test_renounce_guaranteed(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.min_guar = BigInt::from_u32(&env, 50);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // only what has vested is paid out, the guarantee doesn't apply
+    set_timestamp(&env, start + 2);
+    stream_client.with_source_account(&user_2)
+    .renounce(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 20));
+    assert_eq!(token_client.balance(&Identifier::Account(user_1)), BigInt::from_u32(&env, 980));
+    assert_eq!(stream_client.tvl(&token_contract_id), BigInt::zero(&env));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(2))")]
+fn test_renounce_not_recipient(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_1)
+    .renounce(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+}
+
+#[test]
+fn test_active_in(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 10, start, start + 10);
+    stream.able_stop = true;
+    let mut ids = [0u64; 4];
+    for id in ids.iter_mut(){
+        *id = stream_client.with_source_account(&user_1)
+        .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    }
+    // a stream to someone else
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &Stream{ to: Identifier::Account(user_1.clone()), ..stream.clone() });
+
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &ids[1]);
+
+    set_timestamp(&env, start + 11);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &ids[2]);
+    stream_client.with_source_account(&user_2)
+    .cl_stream(&Signature::Invoker, &BigInt::zero(&env), &ids[3]);
+
+    assert_eq!(stream_client.active_in(&Identifier::Account(user_2)), vec![&env, ids[0]]);
+}
+
+#[test]
+fn test_by_seq(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+
+    // 10 per ledger from ledger 10 to 20
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, 10, 20);
+    stream.by_seq = true;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    let set_ledger = |timestamp, sequence_number| env.ledger().set(LedgerInfo {
+        timestamp,
+        protocol_version: 1,
+        sequence_number,
+        network_passphrase: Default::default(),
+        base_reserve: 1,
+    });
+
+    // time doesn't matter
+    set_ledger(15, 10);
+    assert_eq!(stream_client.claimable(&stream_id), BigInt::zero(&env));
+
+    set_ledger(15, 13);
+    assert_eq!(stream_client.claimable(&stream_id), BigInt::from_u32(&env, 30));
+    set_ledger(1000, 13);
+    assert_eq!(stream_client.claimable(&stream_id), BigInt::from_u32(&env, 30));
+
+    set_ledger(1000, 16);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 60));
+}
+
+#[test]
+fn test_by_seq_settings(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .set_mins(&Signature::Invoker, &BigInt::zero(&env), &Some(100), &Some(10));
+    stream_client.with_source_account(&user_1)
+    .set_grace(&Signature::Invoker, &BigInt::zero(&env), &true, &0);
+    set_timestamp(&env, 1000);
+
+    // the minimums and the grace are in seconds, so they don't apply to streams counting ledgers
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, 0, 5);
+    stream.by_seq = true;
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(4))")]
+fn test_signed_create_resubmitted(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let (sender_id, sender) = ed25519::generate(&env);
+
+    token_client.with_source_account(&user_1)
+    .xfer(&Signature::Invoker, &BigInt::zero(&env), &sender_id, &BigInt::from_u32(&env, 200));
+    let nonce = BigInt::zero(&env);
+    let contract = Identifier::Contract(streaming_contract_id.clone());
+    let approve_sig = ed25519::sign(&env, &sender, &token_contract_id, symbol!("approve"), (&sender_id, &nonce, &contract, &BigInt::from_u32(&env, 200)));
+    token_client.approve(&approve_sig, &nonce, &contract, &BigInt::from_u32(&env, 200));
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.from = sender_id.clone();
+
+    let signature = ed25519::sign(&env, &sender, &streaming_contract_id, symbol!("c_stream"), (&sender_id, &nonce, &crate::fingerprint(&env, &stream)));
+    stream_client.c_stream(&signature, &nonce, &stream);
+    stream_client.c_stream(&signature, &nonce, &stream);
+}
+
+#[test]
+fn test_create_once(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let (sender_id, sender) = ed25519::generate(&env);
+
+    token_client.with_source_account(&user_1)
+    .xfer(&Signature::Invoker, &BigInt::zero(&env), &sender_id, &BigInt::from_u32(&env, 200));
+    let nonce = BigInt::zero(&env);
+    let contract = Identifier::Contract(streaming_contract_id.clone());
+    let approve_sig = ed25519::sign(&env, &sender, &token_contract_id, symbol!("approve"), (&sender_id, &nonce, &contract, &BigInt::from_u32(&env, 200)));
+    token_client.approve(&approve_sig, &nonce, &contract, &BigInt::from_u32(&env, 200));
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.from = sender_id.clone();
+    let key = BytesN::from_array(&env, &[1; 32]);
+
+    let signature = ed25519::sign(&env, &sender, &streaming_contract_id, symbol!("c_once"), (&sender_id, &nonce, &crate::fingerprint(&env, &stream), &key));
+    let stream_id = stream_client.c_once(&signature, &nonce, &stream, &key);
+    // the retry doesn't create or fund a second stream
+    assert_eq!(stream_client.c_once(&signature, &nonce, &stream, &key), stream_id);
+    assert_eq!(token_client.balance(&contract), BigInt::from_u32(&env, 100));
+
+    // a new key creates a new stream
+    let nonce = BigInt::from_u32(&env, 1);
+    let key = BytesN::from_array(&env, &[2; 32]);
+    let signature = ed25519::sign(&env, &sender, &streaming_contract_id, symbol!("c_once"), (&sender_id, &nonce, &crate::fingerprint(&env, &stream), &key));
+    assert_ne!(stream_client.c_once(&signature, &nonce, &stream, &key), stream_id);
+    assert_eq!(token_client.balance(&contract), BigInt::from_u32(&env, 200));
+}
+
+#[test]
+fn test_between(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let user_3 = env.accounts().generate();
+
+    token_client.with_source_account(&user_1)
+    .mint(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Account(user_2.clone()), &BigInt::from_u64(&env,100));
+    token_client.with_source_account(&user_2)
+    .approve(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Contract(streaming_contract_id), &BigInt::from_u64(&env,100));
+
+    let start = env.ledger().timestamp();
+    let to_2 = new_stream(&env, &user_1, &user_2, &token_contract_id, 10, start, start + 10);
+    let to_3 = new_stream(&env, &user_1, &user_3, &token_contract_id, 10, start, start + 10);
+    let back = new_stream(&env, &user_2, &user_1, &token_contract_id, 10, start, start + 10);
+
+    let a = stream_client.with_source_account(&user_1).c_stream(&Signature::Invoker, &BigInt::zero(&env), &to_2);
+    let b = stream_client.with_source_account(&user_1).c_stream(&Signature::Invoker, &BigInt::zero(&env), &to_3);
+    let c = stream_client.with_source_account(&user_1).c_stream(&Signature::Invoker, &BigInt::zero(&env), &to_2);
+    let d = stream_client.with_source_account(&user_2).c_stream(&Signature::Invoker, &BigInt::zero(&env), &back);
+
+    let (id_1, id_2, id_3) = (Identifier::Account(user_1), Identifier::Account(user_2), Identifier::Account(user_3));
+    assert_eq!(stream_client.between(&id_1, &id_2), vec![&env, a, c]);
+    assert_eq!(stream_client.between(&id_1, &id_3), vec![&env, b]);
+    assert_eq!(stream_client.between(&id_2, &id_1), vec![&env, d]);
+    assert_eq!(stream_client.between(&id_3, &id_1).len(), 0);
+}
+
+#[test]
+fn test_completed(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 5);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert!(!stream_client.get_stream(&stream_id).1.completed);
+
+    set_timestamp(&env, start + 10);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert!(stream_client.get_stream(&stream_id).1.completed);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(7))")]
+fn test_withdraw_completed(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 10);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+}
+
+#[test]
+fn test_get_many(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 10, start, start + 10);
+    let a = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    let b = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &Stream{ amount: BigInt::from_u32(&env, 20), ..stream.clone() });
+    let closed = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 11);
+    stream_client.with_source_account(&user_2)
+    .cl_stream(&Signature::Invoker, &BigInt::zero(&env), &closed);
+
+    let streams = stream_client.get_many(&vec![&env, b, 42, closed, a]);
+    assert_eq!(streams.len(), 2);
+    assert_eq!(streams.get_unchecked(0).unwrap().0.amount, 20);
+    assert_eq!(streams.get_unchecked(1).unwrap().0.amount, 10);
+    assert_eq!(streams.get_unchecked(1).unwrap().1.a_withdraw, 0);
+}
+
+#[test]
+fn test_enable_cancel(){
+    let env = Env::default();
+    let (user_1, _, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let (recipient_id, recipient) = ed25519::generate(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_1, &token_contract_id, 100, start, start + 10);
+    stream.to = recipient_id.clone();
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    let nonce = BigInt::zero(&env);
+    let to_sig = ed25519::sign(&env, &recipient, &streaming_contract_id, symbol!("en_cancel"), (&recipient_id, &nonce, &stream_id));
+    stream_client.with_source_account(&user_1)
+    .en_cancel(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &to_sig, &nonce);
+    assert!(stream_client.get_stream(&stream_id).0.able_stop);
+
+    set_timestamp(&env, start + 3);
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&recipient_id), BigInt::from_u32(&env, 30));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(2))")]
+fn test_enable_cancel_sender_only(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_1)
+    .en_cancel(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &Signature::Invoker, &BigInt::zero(&env));
+}
+
+#[test]
+// the signature doesn't verify
+#[should_panic(expected = "Status(UnknownError(0))")]
+fn test_enable_cancel_other_stream(){
+    let env = Env::default();
+    let (user_1, _, token_contract_id, _, streaming_contract_id, stream_client) = setup(&env);
+    let (recipient_id, recipient) = ed25519::generate(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_1, &token_contract_id, 100, start, start + 10);
+    stream.to = recipient_id.clone();
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    let other_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // the recipient only agreed for the first stream
+    let nonce = BigInt::zero(&env);
+    let to_sig = ed25519::sign(&env, &recipient, &streaming_contract_id, symbol!("en_cancel"), (&recipient_id, &nonce, &stream_id));
+    stream_client.with_source_account(&user_1)
+    .en_cancel(&Signature::Invoker, &BigInt::zero(&env), &other_id, &to_sig, &nonce);
+}
+
+#[test]
+fn test_withdraw_nothing_new(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.tick_time = 5;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 6);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    let nb_events = env.events().all().len();
+
+    // same tick, nothing is transferred
+    set_timestamp(&env, start + 9);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 50));
+
+    let events = env.events().all();
+    assert_eq!(events.len(), nb_events + 1);
+    let (contract_id, topics, data) = events.last().unwrap().unwrap();
+    assert_eq!(contract_id, streaming_contract_id);
+    assert_eq!(topics, vec![&env, symbol!("stream").into_val(&env), symbol!("no_wdraw").into_val(&env), 1u32.into_val(&env)]);
+    let event_stream_id: u64 = data.try_into_val(&env).unwrap();
+    assert_eq!(event_stream_id, stream_id);
+}
+
+#[test]
+fn test_bond_cancel(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    stream.bond = BigInt::from_u32(&env, 20);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    assert_eq!(token_client.balance(&Identifier::Contract(streaming_contract_id.clone())), BigInt::from_u32(&env, 120));
+    assert_invariants(&env, &stream_client, &streaming_contract_id, &token_contract_id, &[stream_id]);
+
+    set_timestamp(&env, start + 3);
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_invariants(&env, &stream_client, &streaming_contract_id, &token_contract_id, &[stream_id]);
+
+    // 30 vested and the bond
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 50));
+    assert_eq!(token_client.balance(&Identifier::Account(user_1)), BigInt::from_u32(&env, 950));
+    assert_eq!(token_client.balance(&Identifier::Contract(streaming_contract_id)), BigInt::zero(&env));
+    assert_eq!(stream_client.tvl(&token_contract_id), BigInt::zero(&env));
+}
+
+#[test]
+fn test_bond_completed(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    stream.bond = BigInt::from_u32(&env, 20);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 5);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_1.clone())), BigInt::from_u32(&env, 880));
+
+    set_timestamp(&env, start + 10);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 100));
+    assert_eq!(token_client.balance(&Identifier::Account(user_1.clone())), BigInt::from_u32(&env, 900));
+    assert_eq!(token_client.balance(&Identifier::Contract(streaming_contract_id)), BigInt::zero(&env));
+
+    // cancelling afterwards doesn't pay the bond again
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(stream_client.tvl(&token_contract_id), BigInt::zero(&env));
+}
+
+#[test]
+fn test_exact_approval(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+
+    token_client.with_source_account(&user_1)
+    .approve(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Contract(streaming_contract_id), &BigInt::from_u32(&env, 100));
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    assert_eq!(stream_client.tvl(&token_contract_id), BigInt::from_u32(&env, 100));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(32))")]
+fn test_insufficient_approval(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+
+    token_client.with_source_account(&user_1)
+    .approve(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Contract(streaming_contract_id), &BigInt::from_u32(&env, 50));
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+}
+
+#[test]
+fn test_repair(){
+    let env = Env::default();
+    let user_1 = env.accounts().generate();
+    let user_2 = env.accounts().generate();
+    let (token_contract_id, token_client) = create_token_contract(&env, &user_1);
+    let (streaming_contract_id, stream_client) = create_corruptible_streaming_contract(&env);
+    stream_client.init(&Identifier::Account(user_1.clone()));
+
+    token_client.with_source_account(&user_1)
+    .mint(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Account(user_1.clone()), &BigInt::from_u64(&env,1000));
+    token_client.with_source_account(&user_1)
+    .approve(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Contract(streaming_contract_id.clone()), &BigInt::from_u64(&env,1000));
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 3);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    corrupt(&env, &streaming_contract_id, stream_id);
+    assert_eq!(stream_client.corrupted(&0, &1), vec![&env, stream_id]);
+
+    stream_client.with_source_account(&user_1)
+    .repair(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &BigInt::from_u32(&env, 30), &false);
+    assert!(stream_client.corrupted(&0, &1).is_empty());
+    let (_, stream_data) = stream_client.get_stream(&stream_id);
+    assert_eq!(stream_data.a_withdraw, 30);
+    assert!(!stream_data.cancelled);
+
+    // the stream continues where it was
+    set_timestamp(&env, start + 5);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 50));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(33))")]
+fn test_repair_not_corrupted(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+    stream_client.init(&Identifier::Account(user_1.clone()));
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_1)
+    .repair(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &BigInt::from_u32(&env, 100), &false);
+}
+
+#[test]
+fn test_split_to(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+    let user_3 = env.accounts().generate();
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // 40 vested, half of the other 60 goes to user_3
+    set_timestamp(&env, start + 4);
+    let new_id = stream_client.with_source_account(&user_2)
+    .split_to(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &Identifier::Account(user_3.clone()), &5000);
+
+    assert_eq!(stream_client.get_stream(&stream_id).0.amount, 70);
+    let (new_stream, _) = stream_client.get_stream(&new_id);
+    assert_eq!(new_stream.amount, 30);
+    assert_eq!(new_stream.to, Identifier::Account(user_3.clone()));
+    assert_eq!(new_stream.end_time, start + 10);
+    assert_eq!(stream_client.claimable(&stream_id), 40);
+    assert_eq!(stream_client.claimable(&new_id), 0);
+
+    set_timestamp(&env, start + 6);
+    assert_eq!(stream_client.claimable(&stream_id), 50);
+    assert_eq!(stream_client.claimable(&new_id), 10);
+
+    set_timestamp(&env, start + 10);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    stream_client.with_source_account(&user_3)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &new_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 70));
+    assert_eq!(token_client.balance(&Identifier::Account(user_3)), BigInt::from_u32(&env, 30));
+    assert_eq!(stream_client.tvl(&token_contract_id), BigInt::zero(&env));
+}
+
+#[test]
+fn test_split_to_all_bond(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let user_3 = env.accounts().generate();
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.bond = BigInt::from_u32(&env, 20);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 4);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    // everything that hasn't vested moves, which completes the original and releases its bond
+    let new_id = stream_client.with_source_account(&user_2)
+    .split_to(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &Identifier::Account(user_3), &10000);
+
+    assert!(stream_client.get_stream(&stream_id).1.completed);
+    assert_eq!(token_client.balance(&Identifier::Account(user_1)), 900);
+    assert_eq!(stream_client.tvl(&token_contract_id), 60);
+    assert_invariants(&env, &stream_client, &streaming_contract_id, &token_contract_id, &[stream_id, new_id]);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(7))")]
+fn test_split_to_ended(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+    let user_3 = env.accounts().generate();
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 10);
+    stream_client.with_source_account(&user_2)
+    .split_to(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &Identifier::Account(user_3), &5000);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(14))")]
+fn test_split_to_nothing(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+    let user_3 = env.accounts().generate();
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // 0.01% of 100 rounds down to 0
+    stream_client.with_source_account(&user_2)
+    .split_to(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &Identifier::Account(user_3), &1);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(20))")]
+fn test_split_to_max_streams(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+    let user_3 = env.accounts().generate();
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .set_max(&Signature::Invoker, &BigInt::zero(&env), &Some(1));
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // the new stream has the same sender, who is already at the cap
+    stream_client.with_source_account(&user_2)
+    .split_to(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &Identifier::Account(user_3), &5000);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(2))")]
+fn test_split_to_not_recipient(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_1)
+    .split_to(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &Identifier::Account(user_1.clone()), &5000);
+}
+
+#[test]
+fn test_rate(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let per_second = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    let per_tick = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &Stream{ tick_time: 4, ..stream.clone() });
+
+    assert_eq!(stream_client.rate(&per_second), 10);
+    // 3 ticks, the last one is cut short by the end
+    assert_eq!(stream_client.rate(&per_tick), 33);
+
+    // 60 is left for 20 seconds
+    set_timestamp(&env, start + 4);
+    stream_client.with_source_account(&user_1)
+    .e_stream(&Signature::Invoker, &BigInt::zero(&env), &per_second, &(start + 24));
+    assert_eq!(stream_client.rate(&per_second), 3);
+}
+
+#[test]
+fn test_rate_front_loaded(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    // 4 ticks vesting 70, 50, 30 and 10
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 160, start, start + 100);
+    stream.tick_time = 25;
+    stream.curve = Curve::FrontLoad;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    assert_eq!(stream_client.rate(&stream_id), 70);
+    set_timestamp(&env, start + 30);
+    assert_eq!(stream_client.rate(&stream_id), 50);
+    set_timestamp(&env, start + 80);
+    assert_eq!(stream_client.rate(&stream_id), 10);
+    set_timestamp(&env, start + 100);
+    assert_eq!(stream_client.rate(&stream_id), 0);
+}
+
+#[test]
+fn test_rate_no_ticks(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start + 10, start + 20);
+    stream.able_stop = true;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // reducing by everything before the start ends the stream at its start
+    stream_client.with_source_account(&user_1)
+    .r_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &BigInt::from_u32(&env, 100));
+    assert_eq!(stream_client.get_stream(&stream_id).0.end_time, start + 10);
+    assert_eq!(stream_client.rate(&stream_id), 0);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(38))")]
+fn test_rate_milestones(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let milestones = vec![&env,
+        (start + 5, BigInt::from_u32(&env, 60)),
+        (start + 10, BigInt::from_u32(&env, 100)),
+    ];
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_mstone(&Signature::Invoker, &BigInt::zero(&env), &stream, &milestones);
+
+    stream_client.rate(&stream_id);
+}
+
+#[test]
+fn test_self_stream(){
+    let env = Env::default();
+    let (user_1, _, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_1, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 3);
+    stream_client.with_source_account(&user_1)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_1.clone())), BigInt::from_u32(&env, 930));
+
+    set_timestamp(&env, start + 5);
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    assert_eq!(token_client.balance(&Identifier::Account(user_1)), BigInt::from_u32(&env, 1000));
+    assert_eq!(token_client.balance(&Identifier::Contract(streaming_contract_id)), BigInt::zero(&env));
+    assert_eq!(stream_client.tvl(&token_contract_id), BigInt::zero(&env));
+}
+
+#[test]
+fn test_config(){
+    let env = Env::default();
+    let (user_1, _, _, _, _, stream_client) = setup(&env);
+
+    let admin = Identifier::Account(user_1.clone());
+    stream_client.init(&admin);
+
+    let config = stream_client.config();
+    assert_eq!(config.admin, admin);
+    assert!(!config.w_paused);
+    assert_eq!(config.max_strms, u32::MAX);
+    assert_eq!(config.grace, u64::MAX);
+
+    stream_client.with_source_account(&user_1)
+    .set_max(&Signature::Invoker, &BigInt::zero(&env), &Some(3));
+    stream_client.with_source_account(&user_1)
+    .set_wpause(&Signature::Invoker, &BigInt::zero(&env), &true);
+    stream_client.with_source_account(&user_1)
+    .set_mins(&Signature::Invoker, &BigInt::zero(&env), &Some(60), &None);
+    stream_client.with_source_account(&user_1)
+    .set_grace(&Signature::Invoker, &BigInt::zero(&env), &true, &30);
+
+    let config = stream_client.config();
+    assert!(config.w_paused);
+    assert_eq!(config.max_strms, 3);
+    assert_eq!(config.min_dur, 60);
+    assert_eq!(config.min_tick, 0);
+    assert_eq!(config.grace, 30);
+    assert_eq!(config.def_tick, 0);
+    assert_eq!(config.hook, BytesN::from_array(&env, &[0; 32]));
+    assert_eq!(config.wind_down, 0);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(34))")]
+fn test_config_not_init(){
+    let env = Env::default();
+    let (_, _, _, _, _, stream_client) = setup(&env);
+
+    stream_client.config();
+}
+
+#[test]
+fn test_backpay(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+
+    // 100 ticks of 2 seconds, 50 of them are in the past
+    let now = 1000;
+    set_timestamp(&env, now);
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 1000, now - 100, now + 100);
+    stream.tick_time = 2;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2.clone())), BigInt::from_u32(&env, 500));
+
+    // the rest keeps vesting at the same rate
+    set_timestamp(&env, now + 20);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 600));
+}
+
+#[test]
+fn test_requested_cancel(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .set_sdelay(&Signature::Invoker, &BigInt::zero(&env), &Some(30));
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 100);
+    stream.able_stop = true;
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 20);
+    stream_client.with_source_account(&user_1)
+    .req_stop(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    // the recipient can still withdraw while the cancellation is pending
+    set_timestamp(&env, start + 40);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2.clone())), BigInt::from_u32(&env, 40));
+
+    set_timestamp(&env, start + 50);
+    stream_client.with_source_account(&user_1)
+    .fin_stop(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    assert_eq!(token_client.balance(&Identifier::Account(user_1)), BigInt::from_u32(&env, 950));
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 50));
+    assert_eq!(token_client.balance(&Identifier::Contract(streaming_contract_id)), BigInt::zero(&env));
+    assert!(stream_client.get_stream(&stream_id).1.cancelled);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(35))")]
+fn test_requested_cancel_too_early(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 100);
+    stream.able_stop = true;
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_1)
+    .req_stop(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    // the default delay is a day
+    set_timestamp(&env, start + 24 * 60 * 60 - 1);
+    stream_client.with_source_account(&user_1)
+    .fin_stop(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(12))")]
+fn test_cancel_not_requested(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 100);
+    stream.able_stop = true;
+
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_1)
+    .fin_stop(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(36))")]
+fn test_invalid_token(){
+    let env = Env::default();
+    let (user_1, user_2, _, _, _, stream_client) = setup(&env);
+    // a contract without `decimals`
+    let hook_id = env.register_contract(None, TestHook);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &hook_id, 100, start, start + 10);
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+}
+
+#[test]
+fn test_reclaim(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .set_rgrace(&Signature::Invoker, &BigInt::zero(&env), &Some(100));
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 5);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    set_timestamp(&env, start + 111);
+    stream_client.with_source_account(&user_1)
+    .reclaim(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    assert_eq!(token_client.balance(&Identifier::Account(user_1)), BigInt::from_u32(&env, 950));
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 50));
+    assert_eq!(token_client.balance(&Identifier::Contract(streaming_contract_id)), BigInt::zero(&env));
+    assert!(stream_client.get_stream(&stream_id).1.cancelled);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(35))")]
+fn test_reclaim_too_early(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .set_rgrace(&Signature::Invoker, &BigInt::zero(&env), &Some(100));
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 110);
+    stream_client.with_source_account(&user_1)
+    .reclaim(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(7))")]
+fn test_reclaim_withdrawn(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    // the recipient withdraws everything before the grace is over
+    set_timestamp(&env, start + 20);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    set_timestamp(&env, start + 10 + 30 * 24 * 60 * 60 + 1);
+    stream_client.with_source_account(&user_1)
+    .reclaim(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+}
+
+#[test]
+fn test_fingerprint(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let fingerprint = stream_client.fprint(&stream);
+    assert_eq!(stream_client.fprint(&new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10)), fingerprint);
+
+    let mut changed = stream.clone();
+    changed.amount = BigInt::from_u32(&env, 101);
+    assert_ne!(stream_client.fprint(&changed), fingerprint);
+    let mut changed = stream.clone();
+    changed.to = Identifier::Account(user_1.clone());
+    assert_ne!(stream_client.fprint(&changed), fingerprint);
+    let mut changed = stream.clone();
+    changed.end_time += 1;
+    assert_ne!(stream_client.fprint(&changed), fingerprint);
+    let mut changed = stream.clone();
+    changed.able_stop = true;
+    assert_ne!(stream_client.fprint(&changed), fingerprint);
+
+    // matches the hash of the stored stream
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    assert!(stream_client.chk_hash(&stream_id, &fingerprint));
+}
+
+#[test]
+fn test_milestones(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let milestones = vec![&env,
+        (start + 2, BigInt::from_u32(&env, 10)),
+        (start + 5, BigInt::from_u32(&env, 60)),
+        (start + 10, BigInt::from_u32(&env, 100)),
+    ];
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_mstone(&Signature::Invoker, &BigInt::zero(&env), &stream, &milestones);
+
+    set_timestamp(&env, start + 1);
+    assert_eq!(stream_client.claimable(&stream_id), 0);
+    set_timestamp(&env, start + 2);
+    assert_eq!(stream_client.claimable(&stream_id), 10);
+    set_timestamp(&env, start + 4);
+    assert_eq!(stream_client.claimable(&stream_id), 10);
+
+    set_timestamp(&env, start + 6);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2.clone())), BigInt::from_u32(&env, 60));
+
+    set_timestamp(&env, start + 10);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 100));
+    assert!(stream_client.get_stream(&stream_id).1.completed);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(37))")]
+fn test_milestones_decreasing(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let milestones = vec![&env,
+        (start + 2, BigInt::from_u32(&env, 60)),
+        (start + 5, BigInt::from_u32(&env, 10)),
+        (start + 10, BigInt::from_u32(&env, 100)),
+    ];
+    stream_client.with_source_account(&user_1)
+    .c_mstone(&Signature::Invoker, &BigInt::zero(&env), &stream, &milestones);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(37))")]
+fn test_milestones_incomplete(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let milestones = vec![&env,
+        (start + 2, BigInt::from_u32(&env, 10)),
+        (start + 5, BigInt::from_u32(&env, 60)),
+        (start + 10, BigInt::from_u32(&env, 90)),
+    ];
+    stream_client.with_source_account(&user_1)
+    .c_mstone(&Signature::Invoker, &BigInt::zero(&env), &stream, &milestones);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(38))")]
+fn test_milestones_extend(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let milestones = vec![&env, (start + 10, BigInt::from_u32(&env, 100))];
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_mstone(&Signature::Invoker, &BigInt::zero(&env), &stream, &milestones);
+
+    stream_client.with_source_account(&user_1)
+    .e_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &(start + 20));
+}
+
+#[test]
+fn test_refundable(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    for time in [0, 3, 7, 10]{
+        set_timestamp(&env, start + time);
+        assert_eq!(stream_client.refundable(&stream_id) + stream_client.accrued(&stream_id), 100);
+    }
+    set_timestamp(&env, start + 3);
+    assert_eq!(stream_client.refundable(&stream_id), 70);
+
+    // nothing can be taken back from a stream that can't be cancelled
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    assert_eq!(stream_client.refundable(&stream_id), 0);
+}
+
+#[test]
+fn test_summary(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start + 5, start + 15);
+    stream.able_stop = true;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    assert_eq!(stream_client.summary(&stream_id).status, StreamStatus::Pending);
+
+    set_timestamp(&env, start + 8);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    set_timestamp(&env, start + 12);
+
+    let summary = stream_client.summary(&stream_id);
+    assert_eq!(summary.status, StreamStatus::Active);
+    assert_eq!(summary.amount, 100);
+    assert_eq!(summary.withdrawn, 30);
+    assert_eq!(summary.vested, 70);
+    assert_eq!(summary.claimable, 40);
+    assert_eq!(summary.remaining, 70);
+    assert_eq!(summary.withdrawn.clone() + summary.remaining, summary.amount);
+    assert_eq!(summary.withdrawn + summary.claimable, summary.vested);
+
+    set_timestamp(&env, start + 20);
+    assert_eq!(stream_client.summary(&stream_id).status, StreamStatus::Ended);
+
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    let summary = stream_client.summary(&stream_id);
+    assert_eq!(summary.status, StreamStatus::Completed);
+    assert_eq!(summary.remaining, 0);
+    assert_eq!(summary.claimable, 0);
+}
+
+#[test]
+fn test_payout_btw(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp() + 10;
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    assert_eq!(stream_client.payout_btw(&stream_id, &(start + 2), &(start + 6)), 40);
+    // spanning the start and the end
+    assert_eq!(stream_client.payout_btw(&stream_id, &(start - 5), &(start + 3)), 30);
+    assert_eq!(stream_client.payout_btw(&stream_id, &(start + 7), &(start + 20)), 30);
+    assert_eq!(stream_client.payout_btw(&stream_id, &0, &u64::MAX), 100);
+    // outside of the stream
+    assert_eq!(stream_client.payout_btw(&stream_id, &(start - 10), &(start - 1)), 0);
+    assert_eq!(stream_client.payout_btw(&stream_id, &(start + 20), &(start + 30)), 0);
+    assert_eq!(stream_client.payout_btw(&stream_id, &(start + 6), &(start + 2)), 0);
+}
+
+#[test]
+fn test_can_wd_and_stop(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+    let delegate = Identifier::Account(env.accounts().generate());
+    let other = Identifier::Account(env.accounts().generate());
+    let sender = Identifier::Account(user_1.clone());
+    let recipient = Identifier::Account(user_2.clone());
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    stream_client.with_source_account(&user_2)
+    .set_deleg(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &delegate);
+
+    assert!(stream_client.can_wd(&stream_id, &recipient));
+    assert!(stream_client.can_wd(&stream_id, &delegate));
+    assert!(!stream_client.can_wd(&stream_id, &sender));
+    assert!(!stream_client.can_wd(&stream_id, &other));
+
+    assert!(stream_client.can_stop(&stream_id, &sender));
+    assert!(!stream_client.can_stop(&stream_id, &recipient));
+    assert!(!stream_client.can_stop(&stream_id, &delegate));
+    assert!(!stream_client.can_stop(&stream_id, &other));
+
+    // nobody can act on a cancelled stream
+    set_timestamp(&env, start + 3);
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert!(!stream_client.can_wd(&stream_id, &recipient));
+    assert!(!stream_client.can_stop(&stream_id, &sender));
+
+    assert!(!stream_client.can_wd(&(stream_id + 1), &recipient));
+    assert!(!stream_client.can_stop(&(stream_id + 1), &sender));
+}
+
+#[test]
+fn test_reentrant_withdraw(){
+    let env = Env::default();
+    let (user_1, user_2, _, _, streaming_contract_id, stream_client) = setup(&env);
+    let token_id = env.register_contract(None, ReentrantToken);
+    let token = ReentrantTokenClient::new(&env, &token_id);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    token.target(&streaming_contract_id, &stream_id);
+    // the token is allowed to withdraw, so only the order of the checks and effects or the host can stop the
+    // inner withdrawal
+    stream_client.with_source_account(&user_2)
+    .set_deleg(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &Identifier::Contract(token_id.clone()));
+
+    set_timestamp(&env, start + 5);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    // the host doesn't allow the inner withdrawal, and the stream is only paid out once
+    assert!(!token.reentered());
+    assert_eq!(stream_client.get_stream(&stream_id).1.a_withdraw, 50);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(41))")]
+fn test_fee_on_transfer(){
+    let env = Env::default();
+    let (user_1, user_2, _, _, _, stream_client) = setup(&env);
+    let token_id = env.register_contract(None, FeeToken);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_id, 100, start, start + 10);
+    stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+}
+
+#[test]
+fn test_retick(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 100);
+    stream.tick_time = 20;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 30);
+    assert_eq!(stream_client.accrued(&stream_id), 20);
+    set_timestamp(&env, start + 35);
+    assert_eq!(stream_client.accrued(&stream_id), 20);
+
+    stream_client.with_source_account(&user_1)
+    .retick(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &5);
+
+    // the same linear curve, in steps of 5 instead of 20
+    assert_eq!(stream_client.accrued(&stream_id), 35);
+    set_timestamp(&env, start + 39);
+    assert_eq!(stream_client.accrued(&stream_id), 35);
+    set_timestamp(&env, start + 40);
+    assert_eq!(stream_client.accrued(&stream_id), 40);
+    set_timestamp(&env, start + 100);
+    assert_eq!(stream_client.accrued(&stream_id), 100);
+
+    let (stream, _) = stream_client.get_stream(&stream_id);
+    assert_eq!(stream.end_time, start + 100);
+    assert_eq!(stream.amount, 100);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(19))")]
+fn test_retick_too_long(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 100);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 90);
+    stream_client.with_source_account(&user_1)
+    .retick(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &11);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(19))")]
+fn test_retick_zero(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 100);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_1)
+    .retick(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &0);
+}
+
+#[test]
+fn test_retick_by_seq(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    stream_client.init(&Identifier::Account(user_1.clone()));
+    stream_client.with_source_account(&user_1)
+    .set_mins(&Signature::Invoker, &BigInt::zero(&env), &None, &Some(10));
+
+    // the minimum tick is in seconds, so it doesn't apply to ticks counting ledgers
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, 0, 100);
+    stream.by_seq = true;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_1)
+    .retick(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &2);
+    assert_eq!(stream_client.get_stream(&stream_id).0.tick_time, 2);
+}
+
+#[test]
+fn test_withdraw_at_start(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp() + 10;
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start);
+    assert_eq!(stream_client.claimable(&stream_id), 0);
+    assert_eq!(stream_client.accrued(&stream_id), 0);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::zero(&env));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(39))")]
+fn test_withdraw_before_start(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp() + 10;
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start - 1);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(39))")]
+fn test_claimable_before_start(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp() + 10;
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start - 1);
+    stream_client.claimable(&stream_id);
+}
+
+#[test]
+fn test_front_loaded(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let linear = new_stream(&env, &user_1, &user_2, &token_contract_id, 160, start, start + 100);
+    let linear_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &linear);
+    let mut front_loaded = new_stream(&env, &user_1, &user_2, &token_contract_id, 160, start, start + 100);
+    front_loaded.curve = Curve::FrontLoad;
+    let front_loaded_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &front_loaded);
+
+    // 1 - (3/4)^2, 1 - (1/2)^2 and 1 - (1/4)^2 of the amount
+    for (time, linear, front_loaded) in [(25, 40, 70), (50, 80, 120), (75, 120, 150), (100, 160, 160)]{
+        set_timestamp(&env, start + time);
+        assert_eq!(stream_client.accrued(&linear_id), linear);
+        assert_eq!(stream_client.accrued(&front_loaded_id), front_loaded);
+    }
+
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &front_loaded_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 160));
+}
+
+#[test]
+fn test_front_loaded_rounding(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    // the curve never goes down and ends at exactly the amount
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 7, start, start + 13);
+    stream.curve = Curve::FrontLoad;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    let mut last = BigInt::zero(&env);
+    for time in 0..=13{
+        set_timestamp(&env, start + time);
+        let accrued = stream_client.accrued(&stream_id);
+        assert!(accrued >= last);
+        last = accrued;
+    }
+    assert_eq!(last, 7);
+}
+
+#[test]
+fn test_raw_data(){
+    let env = Env::default();
+    let user_1 = env.accounts().generate();
+    let user_2 = env.accounts().generate();
+    let (token_contract_id, token_client) = create_token_contract(&env, &user_1);
+    let (streaming_contract_id, stream_client) = create_corruptible_streaming_contract(&env);
+    stream_client.init(&Identifier::Account(user_1.clone()));
+
+    token_client.with_source_account(&user_1)
+    .mint(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Account(user_1.clone()), &BigInt::from_u64(&env,1000));
+    token_client.with_source_account(&user_1)
+    .approve(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Contract(streaming_contract_id.clone()), &BigInt::from_u64(&env,1000));
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    corrupt(&env, &streaming_contract_id, stream_id);
+    assert_eq!(stream_client.corrupted(&0, &10), vec![&env, stream_id]);
+
+    let raw = stream_client.with_source_account(&user_1)
+    .raw_data(&Signature::Invoker, &stream_id);
+    assert_eq!(raw, Some(BigInt::from_u32(&env, 1).serialize(&env)));
+
+    assert_eq!(stream_client.with_source_account(&user_1).raw_data(&Signature::Invoker, &(stream_id + 1)), None);
+}
+
+#[test]
+fn test_notify_recipient(){
+    let env = Env::default();
+    let (user_1, _, token_contract_id, token_client, _, stream_client) = setup(&env);
+    let vault_id = env.register_contract(None, TestVault);
+    let vault = TestVaultClient::new(&env, &vault_id);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_1, &token_contract_id, 100, start, start + 10);
+    stream.to = Identifier::Contract(vault_id.clone());
+    stream.auto_push = true;
+    stream.notify_to = true;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 11);
+    stream_client.finalize(&stream_id);
+
+    assert_eq!(token_client.balance(&Identifier::Contract(vault_id)), BigInt::from_u32(&env, 100));
+    assert_eq!(vault.total(), 100);
+}
+
+#[test]
+fn test_contract_recipient_not_notified(){
+    let env = Env::default();
+    let (user_1, _, token_contract_id, token_client, _, stream_client) = setup(&env);
+    let vault_id = env.register_contract(None, TestVault);
+    let vault = TestVaultClient::new(&env, &vault_id);
+    // doesn't have `on_recv`
+    let hook_id = env.register_contract(None, TestHook);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_1, &token_contract_id, 100, start, start + 10);
+    stream.to = Identifier::Contract(vault_id.clone());
+    stream.auto_push = true;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    stream.to = Identifier::Contract(hook_id.clone());
+    stream.notify_to = true;
+    let hook_stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 11);
+    stream_client.finalize(&stream_id);
+    stream_client.finalize(&hook_stream_id);
+
+    assert_eq!(token_client.balance(&Identifier::Contract(vault_id)), BigInt::from_u32(&env, 100));
+    assert_eq!(vault.total(), 0);
+    assert_eq!(token_client.balance(&Identifier::Contract(hook_id)), BigInt::from_u32(&env, 100));
+}
+
+fn repaired_stream(env: &Env, a_withdraw: u32) -> (AccountId, StreamingContractClient, u64){
+    let user_1 = env.accounts().generate();
+    let user_2 = env.accounts().generate();
+    let (token_contract_id, token_client) = create_token_contract(env, &user_1);
+    let (streaming_contract_id, stream_client) = create_corruptible_streaming_contract(env);
+    stream_client.init(&Identifier::Account(user_1.clone()));
+
+    token_client.with_source_account(&user_1)
+    .mint(&Signature::Invoker, &BigInt::zero(env), &Identifier::Account(user_1.clone()), &BigInt::from_u64(env,1000));
+    token_client.with_source_account(&user_1)
+    .approve(&Signature::Invoker, &BigInt::zero(env), &Identifier::Contract(streaming_contract_id.clone()), &BigInt::from_u64(env,1000));
+
+    let start = env.ledger().timestamp();
+    let stream = new_stream(env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(env), &stream);
+    corrupt(env, &streaming_contract_id, stream_id);
+    stream_client.with_source_account(&user_1)
+    .repair(&Signature::Invoker, &BigInt::zero(env), &stream_id, &BigInt::from_u32(env, a_withdraw), &false);
+
+    set_timestamp(env, start + 20);
+    (user_2, stream_client, stream_id)
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(7))")]
+fn test_withdraw_all_withdrawn(){
+    let env = Env::default();
+    let (user_2, stream_client, stream_id) = repaired_stream(&env, 100);
+
+    assert!(stream_client.get_stream(&stream_id).1.completed);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(7))")]
+fn test_withdraw_over_amount(){
+    let env = Env::default();
+    let (user_2, stream_client, stream_id) = repaired_stream(&env, 101);
+
+    assert!(stream_client.get_stream(&stream_id).1.completed);
+    assert_eq!(stream_client.claimable(&stream_id), 0);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+}
+
+#[test]
+fn test_close_over_amount(){
+    let env = Env::default();
+    let (user_2, stream_client, stream_id) = repaired_stream(&env, 101);
+
+    // nothing is paid out, the stream is only removed
+    stream_client.with_source_account(&user_2)
+    .cl_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert!(stream_client.get_many(&vec![&env, stream_id]).is_empty());
+}
+
+#[test]
+fn test_vest_time(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 1000, start, start + 100);
+    stream.tick_time = 10;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    assert_eq!(stream_client.vest_time(&stream_id, &BigInt::zero(&env)), start);
+    assert_eq!(stream_client.vest_time(&stream_id, &BigInt::from_u32(&env, 1)), start + 10);
+    assert_eq!(stream_client.vest_time(&stream_id, &BigInt::from_u32(&env, 500)), start + 50);
+    assert_eq!(stream_client.vest_time(&stream_id, &BigInt::from_u32(&env, 501)), start + 60);
+    assert_eq!(stream_client.vest_time(&stream_id, &BigInt::from_u32(&env, 1000)), start + 100);
+    assert_eq!(stream_client.vest_time(&stream_id, &BigInt::from_u32(&env, 1001)), start + 100);
+
+    // consistent with `accrued`
+    for amount in [1, 250, 500, 999]{
+        let time = stream_client.vest_time(&stream_id, &BigInt::from_u32(&env, amount));
+        set_timestamp(&env, time);
+        assert!(stream_client.accrued(&stream_id) >= amount);
+        set_timestamp(&env, time - 1);
+        assert!(stream_client.accrued(&stream_id) < amount);
+    }
+}
+
+#[test]
+fn test_add_funding(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let user_3 = env.accounts().generate();
+    token_client.with_source_account(&user_1)
+    .mint(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Account(user_3.clone()), &BigInt::from_u64(&env,1000));
+    token_client.with_source_account(&user_3)
+    .approve(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Contract(streaming_contract_id.clone()), &BigInt::from_u64(&env,1000));
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_3)
+    .add_fund(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &BigInt::from_u32(&env, 300));
+    assert_eq!(stream_client.get_stream(&stream_id).0.amount, 400);
+
+    // half has vested, the other half goes back 1:3
+    set_timestamp(&env, start + 5);
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), BigInt::from_u32(&env, 200));
+    assert_eq!(token_client.balance(&Identifier::Account(user_1)), BigInt::from_u32(&env, 950));
+    assert_eq!(token_client.balance(&Identifier::Account(user_3)), BigInt::from_u32(&env, 850));
+    assert_eq!(token_client.balance(&Identifier::Contract(streaming_contract_id)), BigInt::zero(&env));
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(7))")]
+fn test_add_funding_completed(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let user_3 = env.accounts().generate();
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.bond = BigInt::from_u32(&env, 20);
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    set_timestamp(&env, start + 4);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    let new_id = stream_client.with_source_account(&user_2)
+    .split_to(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &Identifier::Account(user_3), &10000);
+    assert_eq!(token_client.balance(&Identifier::Account(user_1.clone())), 900);
+    assert_invariants(&env, &stream_client, &streaming_contract_id, &token_contract_id, &[stream_id, new_id]);
+
+    // the bond was already released, reopening the stream would release it again
+    stream_client.with_source_account(&user_1)
+    .add_fund(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &BigInt::from_u32(&env, 10));
+}
+
+#[test]
+fn test_split_to_funders(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, token_client, streaming_contract_id, stream_client) = setup(&env);
+    let user_3 = env.accounts().generate();
+    let user_4 = env.accounts().generate();
+    token_client.with_source_account(&user_1)
+    .mint(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Account(user_3.clone()), &BigInt::from_u64(&env,1000));
+    token_client.with_source_account(&user_3)
+    .approve(&Signature::Invoker, &BigInt::zero(&env), &Identifier::Contract(streaming_contract_id.clone()), &BigInt::from_u64(&env,1000));
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.able_stop = true;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+    stream_client.with_source_account(&user_3)
+    .add_fund(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &BigInt::from_u32(&env, 300));
+
+    let new_id = stream_client.with_source_account(&user_2)
+    .split_to(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &Identifier::Account(user_4), &5000);
+
+    // the refund of the moved half is split 1:3 like the refund of the original
+    stream_client.with_source_account(&user_1)
+    .s_stream(&Signature::Invoker, &BigInt::zero(&env), &new_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_1)), 950);
+    assert_eq!(token_client.balance(&Identifier::Account(user_3)), 850);
+
+    set_timestamp(&env, start + 10);
+    stream_client.with_source_account(&user_2)
+    .w_stream(&Signature::Invoker, &BigInt::zero(&env), &stream_id);
+    assert_eq!(token_client.balance(&Identifier::Account(user_2)), 200);
+    assert_invariants(&env, &stream_client, &streaming_contract_id, &token_contract_id, &[stream_id, new_id]);
+}
+
+#[test]
+#[should_panic(expected = "Status(ContractError(29))")]
+fn test_add_funding_not_prefunded(){
+    let env = Env::default();
+    let (user_1, user_2, token_contract_id, _, _, stream_client) = setup(&env);
+
+    let start = env.ledger().timestamp();
+    let mut stream = new_stream(&env, &user_1, &user_2, &token_contract_id, 100, start, start + 10);
+    stream.prefunded = false;
+    let stream_id = stream_client.with_source_account(&user_1)
+    .c_stream(&Signature::Invoker, &BigInt::zero(&env), &stream);
+
+    stream_client.with_source_account(&user_1)
+    .add_fund(&Signature::Invoker, &BigInt::zero(&env), &stream_id, &BigInt::from_u32(&env, 100));
+}